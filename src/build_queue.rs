@@ -0,0 +1,188 @@
+use crate::config::ProjectType;
+use crate::logging;
+use crate::models::{BuildResult, CommandResult};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use std::time::SystemTime;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingBuild {
+    pub repository_id: Uuid,
+    pub commit_hash: String,
+}
+
+// Builds that have been detected but not yet finished, persisted so a
+// restarted daemon can resume them
+#[derive(Debug, Clone, Default)]
+pub struct BuildQueue {
+    file: Option<PathBuf>,
+    items: Vec<PendingBuild>,
+}
+
+impl BuildQueue {
+    pub fn load(file: PathBuf) -> Self {
+        let items = fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        
+        Self { file: Some(file), items }
+    }
+    
+    fn save(&self) {
+        if let Some(ref file) = self.file {
+            let result = serde_json::to_string_pretty(&self.items)
+                .map_err(|e| e.to_string())
+                .and_then(|content| fs::write(file, content).map_err(|e| e.to_string()));
+            if let Err(e) = result {
+                logging::warn(None, "build_queue_save_failed", &format!("Failed to save build queue: {}", e));
+            }
+        }
+    }
+    
+    pub fn push(&mut self, repository_id: Uuid, commit_hash: &str) {
+        let item = PendingBuild { repository_id, commit_hash: commit_hash.to_string() };
+        if !self.items.contains(&item) {
+            self.items.push(item);
+            self.save();
+        }
+    }
+    
+    pub fn remove(&mut self, repository_id: &Uuid, commit_hash: &str) {
+        let before = self.items.len();
+        self.items.retain(|i| !(&i.repository_id == repository_id && i.commit_hash == commit_hash));
+        if self.items.len() != before {
+            self.save();
+        }
+    }
+    
+    pub fn pending_for(&self, repository_id: &Uuid) -> Vec<PendingBuild> {
+        self.items.iter().filter(|i| &i.repository_id == repository_id).cloned().collect()
+    }
+}
+
+/// State-directory subfolder holding checkpoints of running builds
+pub const CHECKPOINT_DIR: &str = "running";
+
+// The running build as of its last finished step, kept in a scratch file so
+// a daemon that dies mid-build can still record what ran
+pub struct Checkpoint {
+    path: PathBuf,
+    started: SystemTime,
+    build: Mutex<BuildResult>,
+}
+
+impl Checkpoint {
+    pub fn start(dir: &Path, build: BuildResult) -> Self {
+        if let Err(e) = fs::create_dir_all(dir) {
+            logging::warn(Some(&build.repository_name), "checkpoint_failed", &format!("Cannot create {}: {}", dir.display(), e));
+        }
+        let checkpoint = Self {
+            path: dir.join(format!("{}.json", build.id)),
+            started: SystemTime::now(),
+            build: Mutex::new(build),
+        };
+        checkpoint.save(&checkpoint.build.lock().unwrap());
+        checkpoint
+    }
+    
+    /// Adds a finished step; `output` must already be redacted
+    pub fn record(&self, step: &CommandResult, output: &str) {
+        let mut build = self.build.lock().unwrap();
+        build.steps.push(CommandResult { output: output.to_string(), ..step.clone() });
+        build.output.push_str(output);
+        build.duration_ms = self.started.elapsed().map(|elapsed| elapsed.as_millis() as u64).unwrap_or(0);
+        self.save(&build);
+    }
+    
+    /// The build completed and is recorded normally
+    pub fn finish(self) {
+        fs::remove_file(&self.path).ok();
+    }
+    
+    // Written to a temporary file first so a crash mid-write leaves the previous checkpoint
+    fn save(&self, build: &BuildResult) {
+        let temp = self.path.with_extension("tmp");
+        let result = serde_json::to_string(build)
+            .map_err(|e| e.to_string())
+            .and_then(|content| fs::write(&temp, content).map_err(|e| e.to_string()))
+            .and_then(|_| fs::rename(&temp, &self.path).map_err(|e| e.to_string()));
+        if let Err(e) = result {
+            logging::warn(Some(&build.repository_name), "checkpoint_failed", &format!("Failed to save build #{} progress: {}", build.id, e));
+        }
+    }
+}
+
+/// Takes the checkpoints left in `dir` by builds a previous run never finished
+pub fn take_interrupted(dir: &Path) -> Vec<BuildResult> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    
+    let mut builds = Vec::new();
+    for path in entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()) {
+        if path.extension().is_some_and(|ext| ext == "json")
+            && let Some(mut build) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<BuildResult>(&content).ok())
+        {
+            build.success = false;
+            build.interrupted = true;
+            build.output.push_str("⚠️  Interrupted: the daemon stopped before this build finished\n");
+            builds.push(build);
+        }
+        fs::remove_file(&path).ok();
+    }
+    builds
+}
+
+/// Limits on how many builds run at once: one pool per project type with its
+/// own cap, and a shared pool for every other type
+#[derive(Debug, Default)]
+pub struct BuildSlots {
+    shared: Option<Arc<Semaphore>>,
+    by_type: HashMap<ProjectType, Arc<Semaphore>>,
+}
+
+/// A held build slot, given back when dropped
+pub struct BuildSlot {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl BuildSlots {
+    pub fn new(shared: Option<usize>, by_type: &HashMap<ProjectType, usize>) -> Self {
+        Self {
+            shared: shared.map(|permits| Arc::new(Semaphore::new(permits.max(1)))),
+            by_type: by_type
+                .iter()
+                .map(|(project_type, permits)| (project_type.clone(), Arc::new(Semaphore::new((*permits).max(1)))))
+                .collect(),
+        }
+    }
+    
+    fn semaphore(&self, project_type: &ProjectType) -> Option<&Arc<Semaphore>> {
+        self.by_type.get(project_type).or(self.shared.as_ref())
+    }
+    
+    /// A slot for a build of `project_type` if one is free right away
+    pub fn try_acquire(&self, project_type: &ProjectType) -> Option<BuildSlot> {
+        match self.semaphore(project_type) {
+            Some(semaphore) => Arc::clone(semaphore).try_acquire_owned().ok().map(|permit| BuildSlot { _permit: Some(permit) }),
+            None => Some(BuildSlot { _permit: None }),
+        }
+    }
+    
+    /// Waits until a build of `project_type` may start
+    pub async fn acquire(&self, project_type: &ProjectType) -> BuildSlot {
+        match self.semaphore(project_type) {
+            // The semaphores are never closed
+            Some(semaphore) => BuildSlot { _permit: Arc::clone(semaphore).acquire_owned().await.ok() },
+            None => BuildSlot { _permit: None },
+        }
+    }
+}
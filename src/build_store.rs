@@ -0,0 +1,171 @@
+use crate::models::BuildResult;
+use std::path::Path;
+#[cfg(feature = "sqlite")]
+use std::sync::Mutex;
+use uuid::Uuid;
+
+// Optional SQLite persistence for build results, enabled with `storage: "sqlite"`.
+// Builds are stored as JSON alongside the few columns queries filter and sort on.
+pub struct BuildStore {
+    #[cfg(feature = "sqlite")]
+    conn: Mutex<rusqlite::Connection>,
+}
+
+impl std::fmt::Debug for BuildStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BuildStore").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl BuildStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let conn = rusqlite::Connection::open(path).map_err(|e| e.to_string())?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS builds (
+                id INTEGER PRIMARY KEY,
+                repository_id TEXT NOT NULL,
+                repo_build_number INTEGER NOT NULL,
+                success INTEGER NOT NULL,
+                timestamp INTEGER NOT NULL,
+                data TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS builds_by_repository ON builds (repository_id, id);",
+        )
+        .map_err(|e| e.to_string())?;
+
+        Ok(Self { conn: Mutex::new(conn) })
+    }
+
+    pub fn insert(&self, build: &BuildResult) -> Result<(), String> {
+        let data = serde_json::to_string(build).map_err(|e| e.to_string())?;
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR REPLACE INTO builds (id, repository_id, repo_build_number, success, timestamp, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                build.id as i64,
+                build.repository_id.to_string(),
+                build.repo_build_number as i64,
+                build.success,
+                build.timestamp as i64,
+                data,
+            ],
+        )
+        .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Newest first, optionally restricted to one repository
+    pub fn recent(&self, repository_id: Option<&Uuid>, limit: usize, offset: usize) -> Vec<BuildResult> {
+        let conn = self.conn.lock().unwrap();
+        let repository_id = repository_id.map(|id| id.to_string());
+        let mut statement = match conn.prepare(
+            "SELECT data FROM builds WHERE ?1 IS NULL OR repository_id = ?1
+             ORDER BY id DESC LIMIT ?2 OFFSET ?3",
+        ) {
+            Ok(statement) => statement,
+            Err(_) => return Vec::new(),
+        };
+
+        statement
+            .query_map(rusqlite::params![repository_id, limit as i64, offset as i64], |row| row.get::<_, String>(0))
+            .map(|rows| {
+                rows.filter_map(|data| data.ok())
+                    .filter_map(|data| serde_json::from_str(&data).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Newest first, builds of commits whose hash starts with `prefix`
+    pub fn recent_for_commit(&self, prefix: &str, limit: usize, offset: usize) -> Vec<BuildResult> {
+        let conn = self.conn.lock().unwrap();
+        let mut statement = match conn.prepare(
+            "SELECT data FROM builds WHERE substr(json_extract(data, '$.commit_hash'), 1, length(?1)) = ?1
+             ORDER BY id DESC LIMIT ?2 OFFSET ?3",
+        ) {
+            Ok(statement) => statement,
+            Err(_) => return Vec::new(),
+        };
+
+        statement
+            .query_map(rusqlite::params![prefix, limit as i64, offset as i64], |row| row.get::<_, String>(0))
+            .map(|rows| {
+                rows.filter_map(|data| data.ok())
+                    .filter_map(|data| serde_json::from_str(&data).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn get(&self, id: u64) -> Option<BuildResult> {
+        let conn = self.conn.lock().unwrap();
+        let data: String = conn
+            .query_row("SELECT data FROM builds WHERE id = ?1", [id as i64], |row| row.get(0))
+            .ok()?;
+        serde_json::from_str(&data).ok()
+    }
+
+    /// Removes builds started before `timestamp`
+    pub fn delete_before(&self, timestamp: u64) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("DELETE FROM builds WHERE timestamp < ?1", [timestamp as i64])
+            .map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    /// Highest id stored, so ids stay unique across restarts
+    pub fn last_build_id(&self) -> u64 {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row("SELECT COALESCE(MAX(id), 0) FROM builds", [], |row| row.get::<_, i64>(0))
+            .map(|id| id as u64)
+            .unwrap_or(0)
+    }
+
+    pub fn build_count(&self, repository_id: &Uuid) -> u64 {
+        let conn = self.conn.lock().unwrap();
+        conn.query_row(
+            "SELECT COALESCE(MAX(repo_build_number), 0) FROM builds WHERE repository_id = ?1",
+            [repository_id.to_string()],
+            |row| row.get::<_, i64>(0),
+        )
+        .map(|count| count as u64)
+        .unwrap_or(0)
+    }
+}
+
+#[cfg(not(feature = "sqlite"))]
+impl BuildStore {
+    pub fn open(_path: &Path) -> Result<Self, String> {
+        Err("turbulent-ci was built without the `sqlite` feature".to_string())
+    }
+
+    pub fn insert(&self, _build: &BuildResult) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn recent(&self, _repository_id: Option<&Uuid>, _limit: usize, _offset: usize) -> Vec<BuildResult> {
+        Vec::new()
+    }
+
+    pub fn recent_for_commit(&self, _prefix: &str, _limit: usize, _offset: usize) -> Vec<BuildResult> {
+        Vec::new()
+    }
+
+    pub fn get(&self, _id: u64) -> Option<BuildResult> {
+        None
+    }
+
+    pub fn delete_before(&self, _timestamp: u64) -> Result<(), String> {
+        Ok(())
+    }
+
+    pub fn last_build_id(&self) -> u64 {
+        0
+    }
+
+    pub fn build_count(&self, _repository_id: &Uuid) -> u64 {
+        0
+    }
+}
@@ -1,215 +1,2293 @@
-use crate::config::Repository;
-use crate::models::{BuildResult, GlobalState};
-use std::process::Command;
+use crate::build_queue::{self, BuildSlot, Checkpoint};
+use crate::config::{self, ActiveHours, CommandSpec, Config, DedupBy, OutputEncoding, OutputMode, ProjectType, Repository};
+use crate::logging;
+use crate::models::{abbreviate, short_hash, BuildResult, BuildTrigger, CommandResult, GlobalState, SkippedCommit};
+use crate::notifier::{self, Notifier};
+use crate::project_detector::ProjectDetector;
+use crate::repository_manager::RepositoryManager;
+use crate::test_report;
+use base64::Engine;
+use chrono::{DateTime, Local, Utc};
+use futures_util::future::join_all;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-pub type SharedGlobalState = Arc<Mutex<GlobalState>>;
+pub type SharedGlobalState = Arc<tokio::sync::Mutex<GlobalState>>;
 
 pub struct CiRunner {
     repository: Repository,
-    last_commit: Option<String>,
+    configured_repository: Repository,
+    /// Commit or tree hash of the last build, depending on `dedup_by`
+    last_build_key: Option<String>,
     global_state: SharedGlobalState,
-    build_counter: u64,
+    redact_patterns: Vec<Regex>,
+    notifiers: Vec<Notifier>,
+    /// Notifiers paged once this many builds in a row have failed
+    escalation: Vec<(u32, Vec<Notifier>)>,
+    failure_log_tail_lines: usize,
+    notification_cooldown: Option<Duration>,
+    /// When the last failure notification went out
+    last_failure_notified: Option<Instant>,
+    /// Failures not notified about because of `notification_cooldown`
+    suppressed_failures: u32,
+    max_parallel_commands: usize,
+    slow_build_factor: f64,
+    skip_markers: Vec<String>,
+    git: String,
+    disable_missing_after: Option<u32>,
+    /// Consecutive polls that found the repository path gone
+    missing_polls: u32,
+    last_success: Option<bool>,
+    schedule: Option<cron::Schedule>,
+    next_scheduled: Option<DateTime<Utc>>,
+    active_hours: Option<ActiveHours>,
+    /// Whether the last poll fell outside `active_hours`
+    paused: bool,
+    /// Status shown before the daemon was paused, restored on resume
+    status_before_pause: Option<String>,
+    /// Set while building when HEAD moves on, with `supersede_on_new_commit`
+    superseded: Arc<AtomicBool>,
+    /// Where running builds are checkpointed after each step
+    checkpoint_dir: PathBuf,
+    /// Where `last_build_key` and the build count survive restarts
+    saved_state_path: PathBuf,
+    /// For saving changes the runner makes to the repository list
+    config: Config,
 }
 
+// A set of commands run in one directory: the whole repository or one subproject
+struct BuildTarget {
+    label: String,
+    subproject: Option<String>,
+    /// Top of the checkout the target belongs to
+    root: PathBuf,
+    /// Re-runs of past builds, which are never superseded
+    rerun: bool,
+    work_dir: PathBuf,
+    project_type: ProjectType,
+    commands: Vec<CommandSpec>,
+}
+
+// What one run of a command produced
+struct CommandOutput {
+    stdout: String,
+    stderr: String,
+    /// None when the process was killed by a signal
+    exit_code: Option<i32>,
+    success: bool,
+    usage: Option<ResourceUsage>,
+}
+
+// Publishes how far a running build has got. Commands are weighted by how long
+// they took in the last successful build, or equally when any lacks history.
+struct ProgressTracker<'a> {
+    global_state: &'a SharedGlobalState,
+    repo_id: Uuid,
+    weights: Vec<u64>,
+    completed: Mutex<Vec<bool>>,
+}
+
+impl<'a> ProgressTracker<'a> {
+    async fn start(global_state: &'a SharedGlobalState, repo_id: Uuid, target: &BuildTarget) -> Self {
+        let mut state = global_state.lock().await;
+        let history = state.last_step_durations(&repo_id, target.subproject.as_deref());
+        let weights: Option<Vec<u64>> = target.commands
+            .iter()
+            .map(|spec| history.get(&spec.name).map(|&ms| ms.max(1)))
+            .collect();
+        let weights = weights.unwrap_or_else(|| vec![1; target.commands.len()]);
+        state.set_build_progress(&repo_id, 0.0, 0, weights.len());
+        
+        Self {
+            global_state,
+            repo_id,
+            completed: Mutex::new(vec![false; weights.len()]),
+            weights,
+        }
+    }
+    
+    async fn complete(&self, index: usize) {
+        let (progress, steps_completed) = {
+            let mut completed = self.completed.lock().unwrap();
+            completed[index] = true;
+            let done: u64 = self.weights.iter().zip(completed.iter()).filter(|(_, done)| **done).map(|(w, _)| w).sum();
+            let total: u64 = self.weights.iter().sum();
+            let progress = if total == 0 { 1.0 } else { done as f32 / total as f32 };
+            (progress, completed.iter().filter(|done| **done).count())
+        };
+        
+        let mut state = self.global_state.lock().await;
+        state.set_build_progress(&self.repo_id, progress, steps_completed, self.weights.len());
+    }
+}
+
+// Background check for a newer HEAD during a build
+struct CommitWatcher {
+    superseded: Arc<AtomicBool>,
+    handle: JoinHandle<()>,
+}
+
+impl CommitWatcher {
+    /// Ends the watch, returning whether the build was superseded
+    fn stop(self) -> bool {
+        self.handle.abort();
+        self.superseded.load(Ordering::SeqCst)
+    }
+}
+
+// Temporary `git worktree` checkout. Builds remove it when done; one dropped
+// without that, say by a panicking build, is removed in the background.
+struct Worktree {
+    git: String,
+    repo_path: String,
+    path: PathBuf,
+    removed: bool,
+}
+
+impl Worktree {
+    // Points a target at the same directory inside this checkout
+    fn relocate(&self, target: &mut BuildTarget, repo_path: &str) {
+        if let Ok(relative) = target.work_dir.strip_prefix(repo_path) {
+            target.work_dir = self.path.join(relative);
+        }
+        target.root = self.path.clone();
+    }
+    
+    fn remove_command(&self) -> Command {
+        let mut command = Command::new(&self.git);
+        command
+            .args(["worktree", "remove", "--force"])
+            .arg(&self.path)
+            .current_dir(&self.repo_path)
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        command
+    }
+    
+    async fn remove(mut self) {
+        let _ = self.remove_command().status().await;
+        self.removed = true;
+    }
+}
+
+impl Drop for Worktree {
+    fn drop(&mut self) {
+        if !self.removed {
+            let _ = self.remove_command().spawn();
+        }
+    }
+}
+
+// Progress of a step when scheduling by `depends_on`
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StepState {
+    Waiting,
+    Running,
+    Passed,
+    Failed,
+    NotRun,
+}
+
+// Resources used by a command and the descendants it waited for
+#[derive(Debug, Clone, Copy, Default)]
+struct ResourceUsage {
+    peak_memory_kb: u64,
+    cpu_time_ms: u64,
+}
+
+impl ResourceUsage {
+    // Peak memory is the largest seen, CPU time adds up
+    fn combine(a: Option<Self>, b: Option<Self>) -> Option<Self> {
+        match (a, b) {
+            (Some(a), Some(b)) => Some(Self {
+                peak_memory_kb: a.peak_memory_kb.max(b.peak_memory_kb),
+                cpu_time_ms: a.cpu_time_ms + b.cpu_time_ms,
+            }),
+            (a, b) => a.or(b),
+        }
+    }
+}
+
+// How often HEAD is checked while a supersedable build runs
+const SUPERSEDE_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+// How often a running command is checked for having exited, timed out or
+// been superseded
+const EXIT_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+// Env values shorter than this are too likely to appear by coincidence to redact
+const MIN_REDACTED_ENV_LEN: usize = 4;
+
+// What git prints when a remote rejects or can't be given credentials
+const AUTH_FAILURE_PATTERNS: &[&str] = &[
+    "authentication failed",
+    "could not read username",
+    "could not read password",
+    "invalid username or password",
+    "terminal prompts disabled",
+    "permission denied (publickey",
+    "http basic: access denied",
+    "the requested url returned error: 401",
+    "the requested url returned error: 403",
+];
+
+/// A fetch the remote refused for lack of valid credentials
+#[derive(Debug)]
+struct AuthError(String);
+
+impl std::fmt::Display for AuthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for AuthError {}
+
+fn is_auth_failure(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    AUTH_FAILURE_PATTERNS.iter().any(|pattern| stderr.contains(pattern))
+}
+
+// What a runner remembers across daemon restarts, one file per repository
+// under RUNNER_STATE_DIR
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SavedRunnerState {
+    last_build_key: Option<String>,
+    build_count: u64,
+}
+
+const RUNNER_STATE_DIR: &str = "runners";
+
+impl SavedRunnerState {
+    fn load(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+    
+    // Written to a temporary file first so a crash mid-write keeps the previous state
+    fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let temp = path.with_extension("tmp");
+        let content = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        std::fs::write(&temp, content).map_err(|e| e.to_string())?;
+        std::fs::rename(&temp, path).map_err(|e| e.to_string())
+    }
+}
+
+// Temporary worktrees are named `<prefix><repository id>-<name>`
+const WORKTREE_PREFIX: &str = "turbulent-ci-";
+// Commit characters in the name of an isolated build's worktree
+const WORKTREE_HASH_LEN: usize = 12;
+
 impl CiRunner {
-    pub fn new(repository: Repository, global_state: SharedGlobalState) -> Self {
+    pub async fn new(repository: Repository, config: &Config, global_state: SharedGlobalState) -> Self {
         // Initialize repository state
+        let saved_state_path = config.state_file(RUNNER_STATE_DIR).join(format!("{}.json", repository.id));
+        let saved = SavedRunnerState::load(&saved_state_path).unwrap_or_default();
         {
-            let mut state = global_state.lock().unwrap();
+            let mut state = global_state.lock().await;
             state.add_repository_state(repository.clone());
+            state.restore_build_count(&repository.id, saved.build_count);
         }
+        let last_build_key = if config.rebuild_on_start { None } else { saved.last_build_key };
+        
+        let redact_patterns = config.redact_patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(e) => {
+                    logging::warn(Some(&repository.name), "invalid_redact_pattern", &format!("Invalid redact pattern '{}': {}", pattern, e));
+                    None
+                }
+            })
+            .collect();
+        let notifiers = config.notifiers_for(&repository).into_iter().map(Notifier::new).collect();
+        let escalation = repository.escalation
+            .iter()
+            .map(|step| {
+                let notifiers = config.notifiers_named(&step.notifiers).into_iter().map(Notifier::new).collect();
+                (step.after_failures, notifiers)
+            })
+            .collect();
+        let schedule = match repository.parse_schedule() {
+            Some(Ok(schedule)) => Some(schedule),
+            Some(Err(e)) => {
+                logging::warn(Some(&repository.name), "invalid_schedule", &format!("Invalid schedule: {}", e));
+                None
+            }
+            None => None,
+        };
+        let active_hours = match repository.parse_active_hours() {
+            Some(Ok(window)) => Some(window),
+            Some(Err(e)) => {
+                logging::warn(Some(&repository.name), "invalid_active_hours", &format!("Invalid active_hours: {}", e));
+                None
+            }
+            None => None,
+        };
+        let max_parallel_commands = repository.max_parallel_commands.unwrap_or(config.max_parallel_commands);
+        let next_scheduled = schedule.as_ref().and_then(|s| s.upcoming(Utc).next());
         
         Self {
+            configured_repository: repository.clone(),
             repository,
-            last_commit: None,
+            last_build_key,
             global_state,
-            build_counter: 0,
+            redact_patterns,
+            notifiers,
+            escalation,
+            failure_log_tail_lines: config.failure_log_tail_lines,
+            notification_cooldown: config.notification_cooldown_secs.map(Duration::from_secs),
+            last_failure_notified: None,
+            suppressed_failures: 0,
+            max_parallel_commands,
+            slow_build_factor: config.slow_build_factor,
+            skip_markers: config.skip_markers.clone(),
+            git: config.git().to_string(),
+            disable_missing_after: config.disable_missing_after,
+            missing_polls: 0,
+            last_success: None,
+            schedule,
+            next_scheduled,
+            active_hours,
+            paused: false,
+            status_before_pause: None,
+            superseded: Arc::new(AtomicBool::new(false)),
+            checkpoint_dir: config.state_file(build_queue::CHECKPOINT_DIR),
+            saved_state_path,
+            config: config.clone(),
+        }
+    }
+    
+    // Records what was last built, so a restart doesn't build it again
+    async fn remember_build_key(&mut self, build_key: String) {
+        self.last_build_key = Some(build_key);
+        self.save_state().await;
+    }
+    
+    async fn save_state(&self) {
+        let build_count = {
+            let state = self.global_state.lock().await;
+            state.repositories.get(&self.repository.id).map_or(0, |rs| rs.build_count)
+        };
+        let saved = SavedRunnerState { last_build_key: self.last_build_key.clone(), build_count };
+        if let Err(e) = saved.save(&self.saved_state_path) {
+            logging::warn(Some(&self.repository.name), "state_save_failed", &format!("Failed to save runner state: {}", e));
+        }
+    }
+    
+    fn log(&self, event: &str, message: &str) {
+        logging::info(Some(&self.repository.name), event, message);
+    }
+    
+    fn redact(&self, text: &str) -> String {
+        let mut redacted = text.to_string();
+        
+        for value in self.repository.env.values() {
+            if value.len() >= MIN_REDACTED_ENV_LEN {
+                redacted = redacted.replace(value.as_str(), "***REDACTED***");
+            }
+        }
+        
+        for regex in &self.redact_patterns {
+            redacted = regex.replace_all(&redacted, "***REDACTED***").into_owned();
         }
+        
+        redacted
     }
 
-    fn get_latest_commit(&self) -> Result<String, Box<dyn std::error::Error>> {
-        let output = Command::new("git")
-            .args(["rev-parse", "HEAD"])
+    async fn run_git(&self, args: &[&str]) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let output = Command::new(&self.git)
+            .args(args)
             .current_dir(&self.repository.path)
-            .output()?;
+            .output()
+            .await?;
 
         if !output.status.success() {
-            return Err("Failed to get git commit".into());
+            return Err(format!("{} {} failed", self.git, args.join(" ")).into());
         }
 
         Ok(String::from_utf8(output.stdout)?.trim().to_string())
     }
 
-    fn get_current_branch(&self) -> Result<String, Box<dyn std::error::Error>> {
-        let output = Command::new("git")
-            .args(["branch", "--show-current"])
-            .current_dir(&self.repository.path)
-            .output()?;
+    async fn get_latest_commit(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        self.run_git(&["rev-parse", "HEAD"]).await
+            .map_err(|_| "Failed to get git commit".into())
+    }
 
-        if !output.status.success() {
-            return Err("Failed to get git branch".into());
+    // `--show-current` is empty on a detached HEAD, so fall back to the
+    // remote's default branch and finally to a "detached" placeholder
+    async fn get_current_branch(&self) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let current = self.run_git(&["branch", "--show-current"]).await
+            .map_err(|_| -> Box<dyn std::error::Error + Send + Sync> { "Failed to get git branch".into() })?;
+        if !current.is_empty() {
+            return Ok(current);
+        }
+        
+        let fallbacks: [&[&str]; 2] = [
+            &["symbolic-ref", "--short", "refs/remotes/origin/HEAD"],
+            &["rev-parse", "--abbrev-ref", "origin/HEAD"],
+        ];
+        for args in fallbacks {
+            if let Ok(branch) = self.run_git(args).await
+                && !branch.is_empty()
+                && branch != "origin/HEAD"
+            {
+                return Ok(branch.trim_start_matches("origin/").to_string());
+            }
         }
+        
+        Ok("detached".to_string())
+    }
 
-        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+    // Standard variables describing the build; user `env` entries take precedence
+    async fn build_env(&self, commit_hash: &str, build_id: u64, build_number: u64) -> Vec<(String, String)> {
+        let branch = self.get_current_branch().await.unwrap_or_else(|_| "unknown".to_string());
+        vec![
+            ("TURBULENT_BUILD_ID".to_string(), build_id.to_string()),
+            ("TURBULENT_BUILD_NUMBER".to_string(), build_number.to_string()),
+            ("TURBULENT_COMMIT".to_string(), commit_hash.to_string()),
+            ("TURBULENT_REPO_NAME".to_string(), self.repository.name.clone()),
+            ("TURBULENT_BRANCH".to_string(), branch),
+            ("TURBULENT_REPO_PATH".to_string(), self.repository.path.clone()),
+        ]
+    }
+
+    fn build_targets(&self) -> Vec<BuildTarget> {
+        if self.repository.subprojects.is_empty() {
+            return vec![BuildTarget {
+                label: self.repository.name.clone(),
+                subproject: None,
+                root: PathBuf::from(&self.repository.path),
+                rerun: false,
+                work_dir: PathBuf::from(&self.repository.path),
+                project_type: self.repository.project_type.clone(),
+                commands: self.repository.commands.clone(),
+            }];
+        }
+        
+        self.repository.subprojects
+            .iter()
+            .map(|sub| BuildTarget {
+                label: format!("{}/{}", self.repository.name, sub.name),
+                subproject: Some(sub.name.clone()),
+                root: PathBuf::from(&self.repository.path),
+                rerun: false,
+                work_dir: Path::new(&self.repository.path).join(&sub.work_dir),
+                project_type: sub.project_type.clone(),
+                commands: sub.effective_commands(),
+            })
+            .collect()
     }
 
-    fn run_commands(&self, commit_hash: &str) -> BuildResult {
+    async fn run_commands(&self, commit_hash: &str, target: &BuildTarget, trigger: BuildTrigger) -> BuildResult {
+        let _slot = self.acquire_build_slot(target).await;
         let start_time = SystemTime::now();
         let mut all_output = String::new();
         let mut success = true;
+        let mut steps = Vec::new();
+        
+        let (build_id, build_number) = {
+            let mut state = self.global_state.lock().await;
+            state.allocate_build(&self.repository.id)
+        };
+        let build_env = self.build_env(commit_hash, build_id, build_number).await;
+        let mut started = BuildResult {
+            id: build_id,
+            repository_id: self.repository.id,
+            repository_name: self.repository.name.clone(),
+            success: false,
+            output: String::new(),
+            timestamp: start_time.duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            commit_hash: commit_hash.to_string(),
+            duration_ms: 0,
+            repo_path: self.repository.path.clone(),
+            project_type: format!("{:?}", target.project_type),
+            subproject: target.subproject.clone(),
+            steps: Vec::new(),
+            trigger,
+            rerun_of: None,
+            repo_build_number: build_number,
+            note: None,
+            peak_memory_kb: None,
+            cpu_time_ms: None,
+            baseline_duration_ms: None,
+            slow: false,
+            superseded: false,
+            interrupted: false,
+            test_summary: None,
+            environment: HashMap::new(),
+        };
+        
+        if self.repository.output_encoding != OutputEncoding::Utf8 {
+            all_output.push_str(&format!("# Output encoding: {}\n", self.repository.output_encoding.label()));
+        }
 
-        println!("[{}] 🔨 Starting {} build for commit {}...", 
-                 self.repository.name,
-                 format!("{:?}", self.repository.project_type).to_lowercase(),
-                 &commit_hash[..8]);
+        logging::info(Some(&target.label), "build_started", &format!(
+            "🔨 Starting {} build for commit {}...",
+            format!("{:?}", target.project_type).to_lowercase(),
+            short_hash(commit_hash)
+        ));
 
         // Update status
         {
-            let mut state = self.global_state.lock().unwrap();
+            let mut state = self.global_state.lock().await;
             state.update_repository_status(&self.repository.id, "Building...".to_string());
+            state.set_active_build(&self.repository.id, Some(build_id));
         }
 
-        for cmd in &self.repository.commands {
-            println!("[{}] Running: {}", self.repository.name, cmd);
-            
-            let result = self.execute_command(cmd);
-            
-            match result {
-                Ok((stdout, stderr, cmd_success)) => {
-                    all_output.push_str(&format!("=== {} ===\n", cmd));
-                    all_output.push_str(&stdout);
-                    if !stderr.is_empty() {
-                        all_output.push_str("STDERR:\n");
-                        all_output.push_str(&stderr);
-                    }
-                    all_output.push('\n');
-
-                    if !cmd_success {
-                        success = false;
-                        println!("[{}] ❌ Command failed: {}", self.repository.name, cmd);
-                        break;
-                    } else {
-                        println!("[{}] ✅ Command succeeded: {}", self.repository.name, cmd);
-                    }
+        let deadline = self.repository.build_timeout_secs.map(|secs| Instant::now() + Duration::from_secs(secs));
+        started.environment = self.capture_versions(target, &build_env, deadline).await;
+        let submodules_updated = match self.update_submodules(target).await {
+            Some((section, updated)) => {
+                all_output.push_str(&section);
+                updated
+            }
+            None => true,
+        };
+        let checkpoint = Checkpoint::start(&self.checkpoint_dir, BuildResult {
+            output: self.redact(&all_output),
+            ..started.clone()
+        });
+        
+        let watcher = self.watch_for_new_commit(target).await;
+        let progress = ProgressTracker::start(&self.global_state, self.repository.id, target).await;
+        let has_dependencies = target.commands.iter().any(|spec| !spec.depends_on.is_empty());
+        let mut dependency_notes = String::new();
+        let outcomes = if !submodules_updated {
+            success = false;
+            Vec::new()
+        } else if has_dependencies {
+            match config::validate_dependencies(&target.commands) {
+                Ok(_) => {
+                    let (outcomes, notes) = self.run_steps_graph(target, &build_env, deadline, &progress, &checkpoint).await;
+                    dependency_notes = notes;
+                    outcomes
                 }
                 Err(e) => {
                     success = false;
-                    all_output.push_str(&format!("Failed to execute {}: {}\n", cmd, e));
-                    println!("[{}] ❌ Failed to execute: {}", self.repository.name, cmd);
+                    all_output.push_str(&format!("❌ {}\n", e));
+                    logging::error(Some(&target.label), "build_invalid", &e);
+                    Vec::new()
+                }
+            }
+        } else if self.repository.parallel {
+            self.run_steps_parallel(target, &build_env, deadline, &progress, &checkpoint).await
+        } else {
+            let mut outcomes = Vec::new();
+            for (index, spec) in target.commands.iter().enumerate() {
+                if self.should_stop(deadline) {
+                    break;
+                }
+                let outcome = self.run_step(spec, target, &build_env, deadline).await;
+                progress.complete(index).await;
+                checkpoint.record(&outcome.0, &self.redact(&outcome.1));
+                let failed = !outcome.0.success;
+                outcomes.push(outcome);
+                if failed && self.repository.fail_fast {
                     break;
                 }
             }
+            outcomes
+        };
+        let skipped = target.commands.len() - outcomes.len();
+        for (mut step, output) in outcomes {
+            success &= step.success;
+            step.output = self.redact(&output);
+            all_output.push_str(&output);
+            steps.push(step);
         }
+        all_output.push_str(&dependency_notes);
+        if skipped > 0 && deadline_passed(deadline) {
+            success = false;
+            all_output.push_str(&format!(
+                "Build timeout of {}s reached; {} command(s) not run\n",
+                self.repository.build_timeout_secs.unwrap_or_default(),
+                skipped
+            ));
+            logging::info(Some(&target.label), "build_timed_out", "⏱️  Build timeout reached");
+        }
+
+        let superseded = watcher.is_some_and(|watcher| watcher.stop());
+        if superseded {
+            success = false;
+            all_output.push_str("⏭️  Superseded by a newer commit; build cancelled\n");
+            logging::info(Some(&target.label), "build_superseded", "⏭️  Build superseded by a newer commit");
+        }
+        all_output.push_str(&self.run_cleanup(target, &build_env).await);
+        
+        let peak_memory_kb = steps.iter().filter_map(|step| step.peak_memory_kb).max();
+        let cpu_time_ms = steps.iter().filter_map(|step| step.cpu_time_ms).reduce(|a, b| a + b);
 
         let duration = start_time.elapsed().unwrap_or(Duration::from_secs(0));
+        let baseline_duration_ms = {
+            let mut state = self.global_state.lock().await;
+            state.set_active_build(&self.repository.id, None);
+            state.duration_baseline(&self.repository.id, target.subproject.as_deref())
+        };
+        let slow = baseline_duration_ms
+            .is_some_and(|baseline| duration.as_millis() as f64 > baseline as f64 * self.slow_build_factor);
+        if slow {
+            logging::info(Some(&target.label), "build_slow", &format!(
+                "🐢 Build took {}ms, over {}x the {}ms baseline",
+                duration.as_millis(), self.slow_build_factor, baseline_duration_ms.unwrap_or_default()
+            ));
+        }
         
+        checkpoint.finish();
         BuildResult {
-            id: self.build_counter,
-            repository_id: self.repository.id,
-            repository_name: self.repository.name.clone(),
+            test_summary: test_report::parse(&target.project_type, &all_output),
             success,
-            output: all_output,
-            timestamp: start_time.duration_since(UNIX_EPOCH).unwrap().as_secs(),
-            commit_hash: commit_hash.to_string(),
+            output: self.redact(&all_output),
             duration_ms: duration.as_millis() as u64,
-            repo_path: self.repository.path.clone(),
-            project_type: format!("{:?}", self.repository.project_type),
+            steps,
+            peak_memory_kb,
+            cpu_time_ms,
+            baseline_duration_ms,
+            slow,
+            superseded,
+            ..started
         }
     }
     
-    fn execute_command(&self, cmd: &str) -> Result<(String, String, bool), Box<dyn std::error::Error>> {
-        let output = if cfg!(target_os = "windows") {
-            Command::new("cmd")
-                .args(["/C", cmd])
-                .current_dir(&self.repository.path)
-                .output()?
-        } else {
-            Command::new("sh")
-                .args(["-c", cmd])
-                .current_dir(&self.repository.path)
-                .output()?
+    // Waits while as many builds of the target's project type as allowed are
+    // already running
+    async fn acquire_build_slot(&self, target: &BuildTarget) -> BuildSlot {
+        let slots = {
+            let state = self.global_state.lock().await;
+            Arc::clone(&state.build_slots)
         };
+        if let Some(slot) = slots.try_acquire(&target.project_type) {
+            return slot;
+        }
         
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-        let success = output.status.success();
+        logging::info(Some(&target.label), "build_waiting", &format!(
+            "⏳ Waiting for a free {:?} build slot",
+            target.project_type
+        ));
+        {
+            let mut state = self.global_state.lock().await;
+            state.update_repository_status(&self.repository.id, "Waiting for build slot".to_string());
+        }
+        slots.acquire(&target.project_type).await
+    }
+    
+    // Runs `cleanup_commands` like a `finally` block: each one runs whatever
+    // happened before, outside the build timeout, and failures are only logged
+    async fn run_cleanup(&self, target: &BuildTarget, build_env: &[(String, String)]) -> String {
+        if self.repository.cleanup_commands.is_empty() {
+            return String::new();
+        }
         
-        Ok((stdout, stderr, success))
+        let mut section = String::from("=== Cleanup ===\n");
+        for cmd in &self.repository.cleanup_commands {
+            let spec = CommandSpec::new(cmd.clone());
+            logging::info(Some(&target.label), "cleanup_started", &format!("🧹 Cleanup: {}", cmd));
+            match self.execute_command(&spec, &target.work_dir, build_env, None).await {
+                Ok(output) => {
+                    append_command_output(&mut section, &spec, None, &output);
+                    if !output.success {
+                        section.push_str(&format!("Cleanup command exited with {}\n", describe_exit_code(output.exit_code)));
+                        logging::warn(Some(&target.label), "cleanup_failed", &format!(
+                            "Cleanup command failed ({}): {}", describe_exit_code(output.exit_code), cmd
+                        ));
+                    }
+                }
+                Err(e) => {
+                    section.push_str(&format!("=== {} ===\nFailed to run: {}\n", cmd, e));
+                    logging::warn(Some(&target.label), "cleanup_failed", &format!("Cleanup command could not run: {}: {}", cmd, e));
+                }
+            }
+            section.push('\n');
+        }
+        section
     }
-
-    fn check_and_build(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let current_commit = self.get_latest_commit()?;
+    
+    fn should_stop(&self, deadline: Option<Instant>) -> bool {
+        deadline_passed(deadline) || self.superseded.load(Ordering::SeqCst)
+    }
+    
+    // With `supersede_on_new_commit`, polls HEAD in the background while the
+    // build runs. Re-runs of past commits are never superseded.
+    async fn watch_for_new_commit(&self, target: &BuildTarget) -> Option<CommitWatcher> {
+        if !self.repository.supersede_on_new_commit || target.rerun {
+            return None;
+        }
+        let started_at = self.run_git(&["rev-parse", "HEAD"]).await.ok()?;
         
-        if let Some(ref last) = self.last_commit {
-            if last == &current_commit {
-                return Ok(()); // No changes
+        let superseded = Arc::clone(&self.superseded);
+        let git = self.git.clone();
+        let repo_path = self.repository.path.clone();
+        let label = target.label.clone();
+        let handle = tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(SUPERSEDE_CHECK_INTERVAL);
+            // The first tick completes immediately
+            ticks.tick().await;
+            loop {
+                ticks.tick().await;
+                let head = Command::new(&git)
+                    .args(["rev-parse", "HEAD"])
+                    .current_dir(&repo_path)
+                    .output()
+                    .await
+                    .ok()
+                    .filter(|output| output.status.success())
+                    .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+                if let Some(head) = head
+                    && head != started_at
+                {
+                    logging::info(Some(&label), "commit_detected", &format!("📝 New commit {} while building; cancelling", short_hash(&head)));
+                    superseded.store(true, Ordering::SeqCst);
+                    return;
+                }
             }
+        });
+        Some(CommitWatcher { superseded: Arc::clone(&self.superseded), handle })
+    }
+    
+    // Checks out the submodule commits recorded in the build's commit. Returns
+    // the output section and whether it worked, or None without submodules.
+    async fn update_submodules(&self, target: &BuildTarget) -> Option<(String, bool)> {
+        if self.repository.update_submodules == Some(false) || !target.root.join(".gitmodules").exists() {
+            return None;
         }
-
-        println!("[{}] 📝 New commit detected: {}", self.repository.name, &current_commit[..8]);
         
-        self.build_counter += 1;
-        let result = self.run_commands(&current_commit);
+        let mut section = String::from("=== Update submodules ===\n$ git submodule update --init --recursive\n");
+        let updated = match Command::new(&self.git)
+            .args(["submodule", "update", "--init", "--recursive"])
+            .current_dir(&target.root)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
+            .output()
+            .await
+        {
+            Ok(output) => {
+                section.push_str(&String::from_utf8_lossy(&output.stdout));
+                if !output.stderr.is_empty() {
+                    section.push_str("STDERR:\n");
+                    section.push_str(&String::from_utf8_lossy(&output.stderr));
+                }
+                output.status.success()
+            }
+            Err(e) => {
+                section.push_str(&format!("Failed to run {}: {}\n", self.git, e));
+                false
+            }
+        };
+        section.push('\n');
         
-        if result.success {
-            println!("[{}] 🎉 Build successful!", self.repository.name);
-        } else {
-            println!("[{}] 💥 Build failed!", self.repository.name);
+        if !updated {
+            section.push_str("❌ Submodule update failed; no commands were run\n");
+            logging::error(Some(&target.label), "submodule_update_failed", "Submodule update failed");
         }
-
-        // Update state
-        {
-            let mut state = self.global_state.lock().unwrap();
-            state.add_build(result.clone());
-            
-            let status = if result.success {
-                "Passing".to_string()
-            } else {
-                "Failed".to_string()
+        Some((section, updated))
+    }
+    
+    // Runs each `capture_versions` command once, keeping the first line of
+    // output that says something; a failing command records why instead
+    async fn capture_versions(&self, target: &BuildTarget, build_env: &[(String, String)], deadline: Option<Instant>) -> HashMap<String, String> {
+        let mut versions = HashMap::new();
+        for cmd in &self.repository.capture_versions {
+            let value = match self.execute_command(&CommandSpec::new(cmd.as_str()), &target.work_dir, build_env, deadline).await {
+                // Some tools, e.g. `java -version`, print their version to stderr
+                Ok(output) if output.success => [&output.stdout, &output.stderr]
+                    .into_iter()
+                    .flat_map(|text| text.lines())
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .unwrap_or_default()
+                    .to_string(),
+                Ok(output) => format!("unavailable: exited with {}", describe_exit_code(output.exit_code)),
+                Err(e) => format!("unavailable: {}", e),
             };
-            state.update_repository_status(&self.repository.id, status);
-            
-            if let Ok(branch) = self.get_current_branch() {
-                state.update_repository_info(&self.repository.id, branch, current_commit.clone());
-            }
+            versions.insert(cmd.clone(), self.redact(&value));
         }
+        versions
+    }
+    
+    // Runs one command, with retries, returning its result and output section
+    async fn run_step(&self, spec: &CommandSpec, target: &BuildTarget, build_env: &[(String, String)], deadline: Option<Instant>) -> (CommandResult, String) {
+        let cmd = &spec.cmd;
+        let mut all_output = String::new();
+        logging::info(Some(&target.label), "command_started", &format!("Running: {}", spec.name));
+        
+        let step_start = SystemTime::now();
+        let mut attempt = 1;
+        let mut usage = None;
+        let result = loop {
+            let result = self.execute_command(spec, &target.work_dir, build_env, deadline).await;
+            if let Ok(output) = &result {
+                usage = ResourceUsage::combine(usage, output.usage);
+            }
+            match result {
+                Ok(output) if !output.success
+                    && (!spec.optional || !is_command_missing(&output))
+                    && attempt <= self.repository.retries
+                    && self.should_retry(output.exit_code)
+                    && !self.should_stop(deadline) =>
+                {
+                    append_command_output(&mut all_output, spec, Some(attempt), &output);
+                    all_output.push_str(&format!("Attempt {} exited with {}, retrying\n\n", attempt, describe_exit_code(output.exit_code)));
+                    attempt += 1;
+                    logging::info(Some(&target.label), "command_retried", &format!(
+                        "🔁 Retrying {} (attempt {} of {})",
+                        spec.name, attempt, self.repository.retries + 1
+                    ));
+                }
+                result => break result,
+            }
+        };
+        let step_duration = step_start.elapsed().unwrap_or(Duration::from_secs(0));
+        let skipped = spec.optional && match &result {
+            Ok(output) => !output.success && is_command_missing(output),
+            Err(e) => e.downcast_ref::<std::io::Error>().is_some_and(|e| e.kind() == std::io::ErrorKind::NotFound),
+        };
+        let separate = self.repository.output_mode == OutputMode::Separate;
+        let streams = |stream: fn(&CommandOutput) -> &String| match &result {
+            Ok(output) if separate => Some(self.redact(stream(output))),
+            _ => None,
+        };
+        let step = CommandResult {
+            name: spec.name.clone(),
+            command: cmd.clone(),
+            success: skipped || matches!(result, Ok(CommandOutput { success: true, .. })),
+            duration_ms: step_duration.as_millis() as u64,
+            exit_code: result.as_ref().ok().and_then(|output| output.exit_code),
+            attempts: attempt,
+            skipped,
+            peak_memory_kb: usage.map(|usage| usage.peak_memory_kb),
+            cpu_time_ms: usage.map(|usage| usage.cpu_time_ms),
+            output: String::new(),
+            stdout: streams(|output| &output.stdout),
+            stderr: streams(|output| &output.stderr),
+        };
+        
+        match result {
+            Ok(output) => {
+                append_command_output(&mut all_output, spec, None, &output);
+                if self.repository.retries > 0 {
+                    all_output.push_str(&format!("Attempt {} exited with {}\n", attempt, describe_exit_code(output.exit_code)));
+                }
+                all_output.push('\n');
 
-        self.last_commit = Some(current_commit);
-        Ok(())
+                if skipped {
+                    all_output.push_str(&format!("Skipped optional command {}: program not found\n\n", spec.name));
+                    logging::info(Some(&target.label), "command_skipped", &format!("⏭️  Skipped optional command: {} (not installed)", spec.name));
+                } else if !output.success {
+                    if let Some(problem) = describe_script_problem(cmd, &target.work_dir) {
+                        all_output.push_str(&format!("{}\n", problem));
+                    }
+                    logging::info(Some(&target.label), "command_failed", &format!("❌ Command failed: {}", spec.name));
+                } else {
+                    logging::info(Some(&target.label), "command_succeeded", &format!("✅ Command succeeded: {}", spec.name));
+                }
+            }
+            Err(_) if skipped => {
+                all_output.push_str(&format!("Skipped optional command {}: program not found\n\n", spec.name));
+                logging::info(Some(&target.label), "command_skipped", &format!("⏭️  Skipped optional command: {} (not installed)", spec.name));
+            }
+            Err(e) => {
+                match describe_script_problem(cmd, &target.work_dir) {
+                    Some(problem) => all_output.push_str(&format!("{}\n", problem)),
+                    None => all_output.push_str(&format!("Failed to execute {}: {}\n", cmd, e)),
+                }
+                logging::info(Some(&target.label), "command_failed", &format!("❌ Failed to execute: {}", spec.name));
+            }
+        }
+        
+        (step, all_output)
     }
 
-    pub fn run(&mut self) {
-        println!("[{}] 🌪️  Turbulent CI Runner started", self.repository.name);
-        println!("[{}] 📁 Monitoring: {}", self.repository.name, self.repository.path);
-        println!("[{}] 🔧 Project type: {:?}", self.repository.name, self.repository.project_type);
+    // Runs commands on a bounded pool of workers; with fail_fast, commands that
+    // haven't started yet are skipped once one fails. Output keeps command order.
+    async fn run_steps_parallel(&self, target: &BuildTarget, build_env: &[(String, String)], deadline: Option<Instant>, progress: &ProgressTracker<'_>, checkpoint: &Checkpoint) -> Vec<(CommandResult, String)> {
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let slots: Vec<Mutex<Option<(CommandResult, String)>>> =
+            target.commands.iter().map(|_| Mutex::new(None)).collect();
+        let workers = self.max_parallel_commands.clamp(1, target.commands.len().max(1));
         
-        // Initialize status
-        {
-            let mut state = self.global_state.lock().unwrap();
-            state.update_repository_status(&self.repository.id, "Idle".to_string());
-        }
+        join_all((0..workers).map(|_| async {
+            loop {
+                if (self.repository.fail_fast && failed.load(Ordering::SeqCst)) || self.should_stop(deadline) {
+                    break;
+                }
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                let Some(spec) = target.commands.get(index) else {
+                    break;
+                };
+                let outcome = self.run_step(spec, target, build_env, deadline).await;
+                progress.complete(index).await;
+                checkpoint.record(&outcome.0, &self.redact(&outcome.1));
+                if !outcome.0.success {
+                    failed.store(true, Ordering::SeqCst);
+                }
+                *slots[index].lock().unwrap() = Some(outcome);
+            }
+        })).await;
         
-        loop {
-            match self.check_and_build() {
-                Ok(_) => {
-                    let mut state = self.global_state.lock().unwrap();
-                    if let Some(repo_state) = state.repositories.get(&self.repository.id) {
-                        if repo_state.current_status == "Building..." {
-                            state.update_repository_status(&self.repository.id, "Idle".to_string());
+        slots.into_iter().filter_map(|slot| slot.into_inner().unwrap()).collect()
+    }
+    
+    // Runs steps as soon as everything they depend on has passed, up to the
+    // worker cap at once. Steps downstream of a failure are reported and not run.
+    async fn run_steps_graph(&self, target: &BuildTarget, build_env: &[(String, String)], deadline: Option<Instant>, progress: &ProgressTracker<'_>, checkpoint: &Checkpoint) -> (Vec<(CommandResult, String)>, String) {
+        let index_by_name: HashMap<&str, usize> = target.commands
+            .iter()
+            .enumerate()
+            .map(|(index, spec)| (spec.name.as_str(), index))
+            .collect();
+        let dependencies: Vec<Vec<usize>> = target.commands
+            .iter()
+            .map(|spec| spec.depends_on.iter().filter_map(|name| index_by_name.get(name.as_str()).copied()).collect())
+            .collect();
+        
+        let states = Mutex::new(vec![StepState::Waiting; target.commands.len()]);
+        let changed = Notify::new();
+        let notes = Mutex::new(String::new());
+        let slots: Vec<Mutex<Option<(CommandResult, String)>>> =
+            target.commands.iter().map(|_| Mutex::new(None)).collect();
+        let workers = self.max_parallel_commands.clamp(1, target.commands.len().max(1));
+        
+        join_all((0..workers).map(|_| async {
+            loop {
+                let next = loop {
+                    // Registered before the states are checked, so a change made
+                    // in between still wakes this worker
+                    let notified = changed.notified();
+                    {
+                        let mut states = states.lock().unwrap();
+                        mark_unreachable_steps(target, &dependencies, &mut states, &notes);
+                        let stopped = (self.repository.fail_fast && states.contains(&StepState::Failed))
+                            || self.should_stop(deadline);
+                        if stopped || !states.contains(&StepState::Waiting) {
+                            break None;
+                        }
+                        let ready = (0..states.len()).find(|&index| {
+                            states[index] == StepState::Waiting
+                                && dependencies[index].iter().all(|&dependency| states[dependency] == StepState::Passed)
+                        });
+                        if let Some(index) = ready {
+                            states[index] = StepState::Running;
+                            break Some(index);
                         }
                     }
-                },
-                Err(e) => {
-                    println!("[{}] Error: {}", self.repository.name, e);
-                    let mut state = self.global_state.lock().unwrap();
-                    state.update_repository_status(&self.repository.id, format!("Error: {}", e));
-                }
+                    notified.await;
+                };
+                let Some(index) = next else {
+                    changed.notify_waiters();
+                    break;
+                };
+                
+                let outcome = self.run_step(&target.commands[index], target, build_env, deadline).await;
+                progress.complete(index).await;
+                checkpoint.record(&outcome.0, &self.redact(&outcome.1));
+                let state = if outcome.0.success { StepState::Passed } else { StepState::Failed };
+                *slots[index].lock().unwrap() = Some(outcome);
+                states.lock().unwrap()[index] = state;
+                changed.notify_waiters();
+            }
+        })).await;
+        
+        let outcomes = slots.into_iter().filter_map(|slot| slot.into_inner().unwrap()).collect();
+        (outcomes, notes.into_inner().unwrap())
+    }
+    
+    async fn execute_command(&self, spec: &CommandSpec, work_dir: &Path, build_env: &[(String, String)], deadline: Option<Instant>) -> Result<CommandOutput, Box<dyn std::error::Error + Send + Sync>> {
+        let cmd = spec.cmd.as_str();
+        let mut command = if cfg!(target_os = "windows") {
+            let mut command = Command::new("cmd");
+            command.args(["/C", cmd]);
+            command
+        } else {
+            let mut command = Command::new("sh");
+            command.args(["-c", cmd]);
+            command
+        };
+        
+        // Drop to the configured user; the repository's `env` can still override HOME and USER
+        #[cfg(unix)]
+        if let Some(user) = &self.repository.run_as_user {
+            let account = lookup_user(user)?;
+            command
+                .uid(account.uid)
+                .gid(account.gid)
+                .env("HOME", &account.home)
+                .env("USER", user);
+        }
+        // Runs after the user switch, so raising the priority (a negative level) needs root
+        #[cfg(unix)]
+        if let Some(level) = self.repository.nice_level {
+            // SAFETY: setpriority is async-signal-safe and touches no memory of the parent
+            unsafe {
+                command.pre_exec(move || {
+                    if libc::setpriority(libc::PRIO_PROCESS, 0, level) == -1 {
+                        return Err(std::io::Error::last_os_error());
+                    }
+                    Ok(())
+                });
+            }
+        }
+        command
+            .current_dir(work_dir)
+            .envs(build_env.iter().cloned())
+            .envs(&self.repository.env)
+            .stdin(if spec.stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+        let merged = self.repository.output_mode == OutputMode::Merged;
+        let merged_reader = if merged {
+            Some(merge_output(&mut command)?)
+        } else {
+            command.stdout(Stdio::piped()).stderr(Stdio::piped());
+            None
+        };
+        
+        // Run in its own process group so a timeout can kill the whole tree
+        #[cfg(unix)]
+        command.process_group(0);
+        
+        let mut child = command.spawn()?;
+        // The command holds our copies of the merged pipe's write end, which
+        // would keep the reader waiting after the child exits
+        drop(command);
+        let stdout_reader = match merged_reader {
+            Some(reader) => reader,
+            None => read_pipe(child.stdout.take()),
+        };
+        let stderr_reader = read_pipe(child.stderr.take());
+        if let (Some(input), Some(pipe)) = (&spec.stdin, child.stdin.take()) {
+            write_stdin(pipe, input.clone());
+        }
+        
+        // Whichever of the command timeout and the remaining build time is shorter applies
+        let command_timeout = self.repository.timeout_secs.map(Duration::from_secs);
+        let build_remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+        let (timeout, build_limited) = match (command_timeout, build_remaining) {
+            (Some(command), Some(build)) if build < command => (Some(build), true),
+            (None, Some(build)) => (Some(build), true),
+            (command, _) => (command, false),
+        };
+        let cancel = self.repository.supersede_on_new_commit.then_some(self.superseded.as_ref());
+        let (status, timed_out, usage) = wait_with_timeout(&mut child, timeout, cancel).await?;
+        
+        let stdout_bytes = stdout_reader.await.unwrap_or_default();
+        let stderr_bytes = stderr_reader.await.unwrap_or_default();
+        
+        let encoding = self.repository.output_encoding;
+        let mut stdout = decode_output(&stdout_bytes, encoding);
+        let mut stderr = decode_output(&stderr_bytes, encoding);
+        if self.repository.strip_ansi && encoding != OutputEncoding::Base64 {
+            stdout = strip_ansi_codes(&stdout);
+            stderr = strip_ansi_codes(&stderr);
+        }
+        let cancelled = cancel.is_some_and(|cancel| cancel.load(Ordering::SeqCst));
+        // Merged output has no stderr section, so notes follow the output itself
+        let notes = if merged { &mut stdout } else { &mut stderr };
+        if cancelled {
+            notes.push_str("Killed: superseded by a newer commit\n");
+        } else if timed_out && build_limited {
+            notes.push_str(&format!("Killed: build timeout of {}s reached\n", self.repository.build_timeout_secs.unwrap_or_default()));
+        } else if timed_out {
+            notes.push_str(&format!("Command timed out after {}s\n", timeout.unwrap_or_default().as_secs()));
+        }
+        
+        Ok(CommandOutput {
+            stdout,
+            stderr,
+            exit_code: status.code(),
+            success: status.success() && !timed_out && !cancelled,
+            usage,
+        })
+    }
+
+    // An empty list retries any failure
+    fn should_retry(&self, exit_code: Option<i32>) -> bool {
+        let codes = &self.repository.retry_on_exit_codes;
+        codes.is_empty() || exit_code.is_some_and(|code| codes.contains(&code))
+    }
+
+    // Checks `commit` out into a temporary worktree named after this repository
+    async fn add_worktree(&self, name: &str, commit: &str) -> Result<Worktree, Box<dyn std::error::Error + Send + Sync>> {
+        let path = std::env::temp_dir().join(format!("{}{}-{}", WORKTREE_PREFIX, self.repository.id, name));
+        let path_arg = path.to_string_lossy().to_string();
+        self.run_git(&["worktree", "add", "--detach", "--force", &path_arg, commit]).await?;
+        Ok(Worktree {
+            git: self.git.clone(),
+            repo_path: self.repository.path.clone(),
+            path,
+            removed: false,
+        })
+    }
+    
+    // Removes worktrees left behind when the daemon stopped mid-build
+    async fn prune_stale_worktrees(&self) {
+        let Ok(list) = self.run_git(&["worktree", "list", "--porcelain"]).await else {
+            return;
+        };
+        let own_prefix = format!("{}{}-", WORKTREE_PREFIX, self.repository.id);
+        for path in list.lines().filter_map(|line| line.strip_prefix("worktree ")) {
+            let stale = Path::new(path)
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(&own_prefix));
+            if stale {
+                self.log("worktree_pruned", &format!("🧹 Removing stale worktree {}", path));
+                let _ = self.run_git(&["worktree", "remove", "--force", path]).await;
             }
-            
-            thread::sleep(Duration::from_secs(30));
         }
+        let _ = self.run_git(&["worktree", "prune"]).await;
+    }
+
+    // Re-runs past builds in a temporary worktree at their original commit, using
+    // the current commands, so the main working tree is left untouched
+    async fn run_reruns(&mut self) {
+        let requests = {
+            let mut state = self.global_state.lock().await;
+            state.take_rerun_requests(&self.repository.id)
+        };
+        if requests.is_empty() {
+            return;
+        }
+        
+        let previous_status = {
+            let state = self.global_state.lock().await;
+            state.repositories.get(&self.repository.id).map(|rs| rs.current_status.clone())
+        };
+        self.refresh_repo_ci_file();
+        
+        for request in requests {
+            let name = format!("rerun-{}", request.build_id);
+            let worktree = match self.add_worktree(&name, &request.commit_hash).await {
+                Ok(worktree) => worktree,
+                Err(e) => {
+                    logging::error(Some(&self.repository.name), "rerun_failed", &format!(
+                        "Cannot re-run build #{}: commit {} could not be checked out ({})",
+                        request.build_id, short_hash(&request.commit_hash), e
+                    ));
+                    continue;
+                }
+            };
+            
+            self.log("rerun_started", &format!("🔁 Re-running build #{} at commit {}", request.build_id, short_hash(&request.commit_hash)));
+            self.global_state.lock().await.record_event("build_triggered", Some(&self.repository.name), &format!(
+                "Build of {} triggered: re-run of build #{}", short_hash(&request.commit_hash), request.build_id
+            ));
+            let targets: Vec<_> = self.build_targets()
+                .into_iter()
+                .filter(|target| target.subproject == request.subproject)
+                .collect();
+            for mut target in targets {
+                worktree.relocate(&mut target, &self.repository.path);
+                target.rerun = true;
+                let mut result = self.run_commands(&request.commit_hash, &target, BuildTrigger::Manual).await;
+                result.rerun_of = Some(request.build_id);
+                
+                let mut state = self.global_state.lock().await;
+                state.add_build(result);
+            }
+            worktree.remove().await;
+        }
+        
+        if let Some(status) = previous_status {
+            let mut state = self.global_state.lock().await;
+            state.update_repository_status(&self.repository.id, status);
+        }
+        // Re-runs take build numbers too
+        self.save_state().await;
+    }
+
+    // Why a new commit shouldn't be built: its message carries a marker such as
+    // `[skip ci]`, or the files it changed don't pass the path filters
+    async fn skip_reason(&self, build_key: &str) -> Option<String> {
+        if let Ok(message) = self.run_git(&["log", "-1", "--format=%B"]).await
+            && let Some(marker) = self.skip_markers.iter().find(|marker| message.contains(marker.as_str()))
+        {
+            return Some(format!("message contains {}", marker));
+        }
+        
+        if let Some(ref filter) = self.repository.author_filter
+            && let Ok(author) = self.run_git(&["log", "-1", "--format=%an%n%ae"]).await
+        {
+            let (name, email) = author.split_once('\n').unwrap_or((&author, ""));
+            if let Some(reason) = filter.rejects(name, email) {
+                return Some(reason);
+            }
+        }
+        
+        if self.repository.trigger_paths.is_empty() && self.repository.ignore_paths.is_empty() {
+            return None;
+        }
+        // Without a previous build (or if it's gone from history) there's nothing to diff against
+        let last = self.last_build_key.as_ref()?;
+        let diff = self.run_git(&["diff", "--name-only", last, build_key]).await.ok()?;
+        let changed: Vec<&str> = diff.lines().filter(|line| !line.is_empty()).collect();
+        path_filter_reason(&self.repository.trigger_paths, &self.repository.ignore_paths, &changed)
+    }
+
+    // Reports whether the cron schedule has fired since the last check
+    fn take_scheduled_run(&mut self) -> bool {
+        match self.next_scheduled {
+            Some(next) if Utc::now() >= next => {
+                self.next_scheduled = self.schedule.as_ref().and_then(|s| s.upcoming(Utc).next());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Builds run against the working tree, so uncommitted changes are worth surfacing
+    async fn refresh_working_tree_state(&self) {
+        if let Ok(status) = self.run_git(&["status", "--porcelain"]).await {
+            let changed_files = status.lines().filter(|l| !l.trim().is_empty()).count();
+            let mut state = self.global_state.lock().await;
+            state.update_working_tree(&self.repository.id, changed_files);
+        }
+        
+        let ahead_behind = self.ahead_behind().await;
+        let mut state = self.global_state.lock().await;
+        state.update_upstream(&self.repository.id, ahead_behind);
+    }
+    
+    // Commits ahead of and behind the branch's upstream, if it has one
+    async fn ahead_behind(&self) -> Option<(u32, u32)> {
+        let counts = self.run_git(&["rev-list", "--left-right", "--count", "@{u}...HEAD"]).await.ok()?;
+        let mut counts = counts.split_whitespace().map(|count| count.parse::<u32>().ok());
+        let behind = counts.next()??;
+        let ahead = counts.next()??;
+        Some((ahead, behind))
+    }
+
+    // Re-queues builds interrupted by a previous shutdown, dropping commits that
+    // have since disappeared (e.g. force-pushed away)
+    async fn resume_pending_builds(&self) {
+        let pending = {
+            let state = self.global_state.lock().await;
+            state.build_queue.pending_for(&self.repository.id)
+        };
+        
+        for item in pending {
+            let reachable = self.run_git(&["cat-file", "-e", &format!("{}^{{commit}}", item.commit_hash)]).await.is_ok();
+            let mut state = self.global_state.lock().await;
+            if reachable {
+                self.log("build_resumed", &format!("♻️  Resuming queued build for commit {}", short_hash(&item.commit_hash)));
+                state.request_build(&self.repository.id);
+            } else {
+                self.log("queued_commit_skipped", &format!("Skipping queued commit {}: no longer reachable", short_hash(&item.commit_hash)));
+            }
+            state.build_queue.remove(&self.repository.id, &item.commit_hash);
+        }
+    }
+
+    // With `auto_redetect`, follows the project to a new type (say a Cargo.toml
+    // appearing) and saves the change, default commands included
+    async fn redetect_project_type(&mut self) {
+        let detected = ProjectDetector::new().detect_project_type(&self.configured_repository.path).project_type;
+        if detected == self.configured_repository.project_type {
+            return;
+        }
+        
+        let previous = self.configured_repository.project_type.clone();
+        self.configured_repository.change_project_type(detected.clone());
+        self.log("project_type_changed", &format!(
+            "🔍 Project type changed from {:?} to {:?}{}",
+            previous,
+            detected,
+            if self.configured_repository.has_default_commands() { "; using its default commands" } else { "; keeping custom commands" }
+        ));
+        
+        if let Err(e) = RepositoryManager::update_stored(&self.config, &self.repository.id, |stored| {
+            stored.change_project_type(detected.clone());
+        }) {
+            logging::warn(Some(&self.repository.name), "project_type_save_failed", &format!("Failed to save the new project type: {}", e));
+        }
+        let mut state = self.global_state.lock().await;
+        state.update_repository_definition(&self.configured_repository);
+    }
+
+    // Re-reads `.turbulent-ci.toml` so changes committed to it apply to this build
+    fn refresh_repo_ci_file(&mut self) {
+        self.repository = self.configured_repository.clone().with_repo_ci_file();
+    }
+
+    // Managed clones track origin: fetch and move the working tree to the remote branch
+    async fn sync_remote(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        // Without a terminal, git would otherwise wait forever for credentials
+        let output = Command::new(&self.git)
+            .args(["fetch", "--quiet", "origin"])
+            .current_dir(&self.repository.path)
+            .env("GIT_TERMINAL_PROMPT", "0")
+            .stdin(Stdio::null())
+            .output()
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            if is_auth_failure(&stderr) {
+                return Err(Box::new(AuthError("authentication failed fetching origin".to_string())));
+            }
+            return Err(format!("Failed to fetch origin: {}", stderr.trim()).into());
+        }
+        
+        let branch = self.get_current_branch().await?;
+        let upstream = format!("origin/{}", branch);
+        let remote_commit = self.run_git(&["rev-parse", &upstream]).await?;
+        if remote_commit != self.get_latest_commit().await? {
+            self.run_git(&["reset", "--hard", "--quiet", &upstream]).await?;
+        }
+        
+        Ok(())
+    }
+
+    async fn check_and_build(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if self.repository.remote_url.is_some() {
+            self.sync_remote().await?;
+        }
+        if self.defer_while_paused().await? {
+            return Ok(());
+        }
+        self.run_reruns().await;
+        let current_commit = self.get_latest_commit().await?;
+        self.refresh_working_tree_state().await;
+        let (manual, webhook) = {
+            let mut state = self.global_state.lock().await;
+            (state.take_build_request(&self.repository.id), state.take_webhook(&self.repository.id))
+        };
+        let scheduled = self.take_scheduled_run();
+        let build_key = match self.repository.dedup_by {
+            DedupBy::Commit => current_commit.clone(),
+            DedupBy::Tree => self.run_git(&["rev-parse", "HEAD^{tree}"]).await?,
+        };
+        let new_commit = self.last_build_key.as_ref() != Some(&build_key);
+        if new_commit && self.repository.auto_redetect {
+            self.redetect_project_type().await;
+        }
+        
+        let trigger = if manual {
+            self.log("build_triggered", &format!("🚀 Build triggered for commit: {}", short_hash(&current_commit)));
+            BuildTrigger::Manual
+        } else if new_commit {
+            self.log("commit_detected", &format!("📝 New commit detected: {}", short_hash(&current_commit)));
+            if let Some(reason) = self.skip_reason(&build_key).await {
+                self.log("build_skipped", &format!("⏭️  Skipping commit {}: {}", short_hash(&current_commit), reason));
+                let branch = self.get_current_branch().await.unwrap_or_else(|_| "unknown".to_string());
+                {
+                    let mut state = self.global_state.lock().await;
+                    state.record_event("build_skipped", Some(&self.repository.name), &format!(
+                        "Commit {} skipped: {}", short_hash(&current_commit), reason
+                    ));
+                    state.set_skipped_commit(&self.repository.id, Some(SkippedCommit { commit_hash: current_commit.clone(), reason }));
+                    state.update_repository_info(&self.repository.id, branch, current_commit);
+                }
+                self.remember_build_key(build_key).await;
+                return Ok(());
+            }
+            if webhook { BuildTrigger::Webhook } else { BuildTrigger::Commit }
+        } else if scheduled {
+            self.log("build_scheduled", &format!("⏰ Scheduled build for commit: {}", short_hash(&current_commit)));
+            BuildTrigger::Scheduled
+        } else {
+            return Ok(()); // No changes
+        };
+        
+        self.refresh_repo_ci_file();
+        // An empty build would pass without testing anything
+        if self.repository.has_no_commands() {
+            {
+                let mut state = self.global_state.lock().await;
+                let already_reported = state.repositories
+                    .get(&self.repository.id)
+                    .is_some_and(|rs| rs.current_status == "NoCommands");
+                if !already_reported {
+                    logging::warn(Some(&self.repository.name), "no_commands", &format!(
+                        "No commands configured; not building commit {}",
+                        short_hash(&current_commit)
+                    ));
+                }
+                state.update_repository_status(&self.repository.id, "NoCommands".to_string());
+            }
+            self.remember_build_key(build_key).await;
+            return Ok(());
+        }
+        {
+            let mut state = self.global_state.lock().await;
+            let reason = match trigger {
+                BuildTrigger::Manual => "requested manually",
+                BuildTrigger::Scheduled => "scheduled",
+                BuildTrigger::Webhook => "new commit reported by webhook",
+                _ => "new commit",
+            };
+            state.record_event("build_triggered", Some(&self.repository.name), &format!(
+                "Build of {} triggered: {}", short_hash(&current_commit), reason
+            ));
+            state.build_queue.push(self.repository.id, &current_commit);
+        }
+        
+        // Subprojects share the commit but each produce their own build
+        let worktree = if self.repository.isolated {
+            match self.add_worktree(abbreviate(&current_commit, WORKTREE_HASH_LEN), &current_commit).await {
+                Ok(worktree) => Some(worktree),
+                Err(e) => {
+                    let mut state = self.global_state.lock().await;
+                    state.build_queue.remove(&self.repository.id, &current_commit);
+                    return Err(format!("Cannot create isolated checkout: {}", e).into());
+                }
+            }
+        } else {
+            None
+        };
+        let mut results = Vec::new();
+        for mut target in self.build_targets() {
+            if let Some(worktree) = &worktree {
+                worktree.relocate(&mut target, &self.repository.path);
+            }
+            let result = self.run_commands(&current_commit, &target, trigger).await;
+            
+            if result.superseded {
+                results.push(result);
+                break;
+            } else if result.success {
+                logging::info(Some(&target.label), "build_succeeded", "🎉 Build successful!");
+            } else {
+                logging::info(Some(&target.label), "build_failed", "💥 Build failed!");
+            }
+            
+            self.notify(&result).await;
+            self.send_outcome_webhook(&result);
+            results.push(result);
+        }
+        if let Some(worktree) = worktree {
+            worktree.remove().await;
+        }
+        
+        if results.iter().any(|r| r.superseded) {
+            let mut state = self.global_state.lock().await;
+            for result in results {
+                state.add_build(result);
+            }
+            state.build_queue.remove(&self.repository.id, &current_commit);
+            return Ok(());
+        }
+        
+        let success = results.iter().all(|r| r.success);
+        self.last_success = Some(success);
+        let latest = results.iter().find(|r| !r.success).or(results.last()).cloned();
+        let previous_failures;
+        let consecutive_failures;
+
+        // Update state
+        let branch = self.get_current_branch().await;
+        {
+            let mut state = self.global_state.lock().await;
+            for result in results {
+                state.add_build(result);
+            }
+            state.build_queue.remove(&self.repository.id, &current_commit);
+            state.set_skipped_commit(&self.repository.id, None);
+            
+            // Failures below the threshold only mark the repository unstable
+            previous_failures = state.repositories
+                .get(&self.repository.id)
+                .map_or(0, |rs| rs.consecutive_failures);
+            consecutive_failures = state.record_build_outcome(&self.repository.id, success);
+            let status = if success {
+                "Passing".to_string()
+            } else if consecutive_failures >= self.repository.consecutive_failures_to_fail {
+                "Failed".to_string()
+            } else {
+                "Unstable".to_string()
+            };
+            state.update_repository_status(&self.repository.id, status);
+            
+            if let Ok(branch) = branch {
+                state.update_repository_info(&self.repository.id, branch, current_commit.clone());
+            }
+        }
+
+        if let Some(build) = latest {
+            self.escalate(previous_failures, consecutive_failures, &build).await;
+        }
+
+        self.remember_build_key(build_key).await;
+        Ok(())
+    }
+
+    // Unlike notifications these are for automation, so every build is sent
+    fn send_outcome_webhook(&self, result: &BuildResult) {
+        let url = if result.success { &self.repository.on_success_webhook } else { &self.repository.on_failure_webhook };
+        if let Some(url) = url {
+            notifier::send_outcome_webhook(url.clone(), result.clone());
+        }
+    }
+    
+    // Notifies on failures, at most once per cooldown, and on the first
+    // success after a failure
+    async fn notify(&mut self, result: &BuildResult) {
+        let message = if !result.success {
+            if let (Some(cooldown), Some(notified)) = (self.notification_cooldown, self.last_failure_notified)
+                && notified.elapsed() < cooldown
+            {
+                self.suppressed_failures += 1;
+                self.log("notification_throttled", &format!(
+                    "🔕 Not notifying about build #{}: last failure notification was {}s ago",
+                    result.id, notified.elapsed().as_secs()
+                ));
+                return;
+            }
+            
+            let mut message = format!("❌ {} build #{} failed on {}", self.repository.name, result.id, short_hash(&result.commit_hash));
+            if self.suppressed_failures > 0 {
+                message.push_str(&format!(" ({} more failed since the last notification)", self.suppressed_failures));
+            }
+            if let Some(tail) = notifier::failure_tail(result, self.failure_log_tail_lines) {
+                message.push_str(&format!("\n```\n{}\n```", tail));
+            }
+            self.last_failure_notified = Some(Instant::now());
+            self.suppressed_failures = 0;
+            message
+        } else if self.last_success == Some(false) {
+            let mut message = format!("✅ {} recovered: build #{} passed on {}", self.repository.name, result.id, short_hash(&result.commit_hash));
+            if self.suppressed_failures > 0 {
+                message.push_str(&format!(" ({} failures since the last notification weren't sent)", self.suppressed_failures));
+            }
+            // The next outage is announced straight away
+            self.last_failure_notified = None;
+            self.suppressed_failures = 0;
+            message
+        } else {
+            return;
+        };
+        
+        self.send_to(&self.notifiers, result, &message).await;
+    }
+    
+    // Pages each escalation step as the failure streak reaches it, and lets
+    // every step that was paged know once the repository recovers
+    async fn escalate(&self, previous_failures: u32, consecutive_failures: u32, build: &BuildResult) {
+        for (after_failures, notifiers) in &self.escalation {
+            let message = if consecutive_failures == *after_failures {
+                let mut message = format!(
+                    "🚨 {} has failed {} builds in a row (latest #{} on {})",
+                    self.repository.name, consecutive_failures, build.id, short_hash(&build.commit_hash)
+                );
+                if let Some(tail) = notifier::failure_tail(build, self.failure_log_tail_lines) {
+                    message.push_str(&format!("\n```\n{}\n```", tail));
+                }
+                message
+            } else if consecutive_failures == 0 && previous_failures >= *after_failures {
+                format!(
+                    "✅ {} recovered after {} failed builds: build #{} passed on {}",
+                    self.repository.name, previous_failures, build.id, short_hash(&build.commit_hash)
+                )
+            } else {
+                continue;
+            };
+            self.send_to(notifiers, build, &message).await;
+        }
+    }
+    
+    async fn send_to(&self, notifiers: &[Notifier], build: &BuildResult, message: &str) {
+        for notifier in notifiers {
+            match notifier.send(build, message).await {
+                Ok(()) => {
+                    let summary = message.lines().next().unwrap_or_default();
+                    self.global_state.lock().await.record_event("notification_sent", Some(&self.repository.name), &format!(
+                        "Notified '{}': {}", notifier.name(), summary
+                    ));
+                }
+                Err(e) => logging::warn(Some(&self.repository.name), "notification_failed", &format!("Notifier '{}' failed: {}", notifier.name(), e)),
+            }
+        }
+    }
+
+    /// Polls the repository until the daemon exits
+    pub async fn run(mut self) {
+        self.start().await;
+        loop {
+            if !self.poll().await {
+                self.wait_for_next_poll().await;
+            }
+        }
+    }
+    
+    async fn start(&mut self) {
+        self.log("runner_started", "🌪️  Turbulent CI Runner started");
+        self.log("runner_started", &format!("📁 Monitoring: {}", self.repository.path));
+        self.log("runner_started", &format!("🔧 Project type: {:?}", self.repository.project_type));
+        
+        // Initialize status
+        {
+            let mut state = self.global_state.lock().await;
+            state.update_repository_status(&self.repository.id, "Idle".to_string());
+        }
+        self.prune_stale_worktrees().await;
+        self.resume_pending_builds().await;
+    }
+    
+    // One round of checking for and building new work. Returns true when the
+    // next round should start without waiting.
+    async fn poll(&mut self) -> bool {
+        if !self.check_path_present().await || !self.check_active_hours().await {
+            return false;
+        }
+        
+        match self.check_and_build().await {
+            Ok(_) => {
+                let mut state = self.global_state.lock().await;
+                if let Some(repo_state) = state.repositories.get(&self.repository.id)
+                    && repo_state.current_status == "Building..."
+                {
+                    state.update_repository_status(&self.repository.id, "Idle".to_string());
+                }
+            },
+            Err(e) if e.is::<AuthError>() => {
+                logging::error(Some(&self.repository.name), "auth_failed", &format!(
+                    "🔑 {}; check the credentials or SSH key for {}",
+                    e, self.repository.remote_url.as_deref().unwrap_or("origin")
+                ));
+                let mut state = self.global_state.lock().await;
+                state.update_repository_status(&self.repository.id, format!("AuthError: {}", e));
+            }
+            Err(e) => {
+                logging::error(Some(&self.repository.name), "poll_failed", &e.to_string());
+                let mut state = self.global_state.lock().await;
+                state.update_repository_status(&self.repository.id, format!("Error: {}", e));
+            }
+        }
+        
+        // Go straight on to the commit that replaced the cancelled build
+        self.superseded.swap(false, Ordering::SeqCst)
+    }
+    
+    // While the daemon is paused, keeps following HEAD without building;
+    // the builds and re-runs requested meanwhile wait for the resume, and
+    // the first poll after it builds the latest commit
+    async fn defer_while_paused(&mut self) -> Result<bool, Box<dyn std::error::Error + Send + Sync>> {
+        let paused = self.global_state.lock().await.paused;
+        if !paused {
+            if let Some(status) = self.status_before_pause.take() {
+                self.log("builds_resumed", "▶️  Daemon resumed");
+                let mut state = self.global_state.lock().await;
+                state.update_repository_status(&self.repository.id, status);
+            }
+            return Ok(false);
+        }
+        
+        let current_commit = self.get_latest_commit().await?;
+        let branch = self.get_current_branch().await.unwrap_or_else(|_| "unknown".to_string());
+        self.refresh_working_tree_state().await;
+        let mut state = self.global_state.lock().await;
+        if self.status_before_pause.is_none() {
+            self.log("builds_paused", "⏸️  Daemon paused; deferring builds");
+            self.status_before_pause = state.repositories.get(&self.repository.id).map(|rs| rs.current_status.clone());
+            state.update_repository_status(&self.repository.id, "Paused".to_string());
+        }
+        state.update_repository_info(&self.repository.id, branch, current_commit);
+        Ok(true)
+    }
+    
+    // Outside `active_hours` new commits and scheduled runs wait for the window
+    // to open. Explicit triggers and re-runs still go ahead.
+    async fn check_active_hours(&mut self) -> bool {
+        let Some(window) = self.active_hours else {
+            return true;
+        };
+        let mut state = self.global_state.lock().await;
+        let requested = state.repositories
+            .get(&self.repository.id)
+            .is_some_and(|rs| rs.build_requested || !rs.rerun_requests.is_empty());
+        let active = window.contains(Local::now().time());
+        
+        if !active && !requested {
+            if !self.paused {
+                self.log("polling_paused", &format!(
+                    "⏸️  Outside active hours ({}); deferring builds",
+                    self.repository.active_hours.as_deref().unwrap_or_default()
+                ));
+                state.update_repository_status(&self.repository.id, "Paused (schedule)".to_string());
+                self.paused = true;
+            }
+            return false;
+        }
+        
+        if active && self.paused {
+            self.log("polling_resumed", "▶️  Active hours started; resuming");
+            state.update_repository_status(&self.repository.id, "Idle".to_string());
+        }
+        // A triggered build outside the window replaces the paused status, so re-pause afterwards
+        self.paused = false;
+        true
+    }
+    
+    // A deleted or unmounted path gets a `Missing` status instead of a git error
+    // every poll, and is optionally disabled until the path comes back
+    async fn check_path_present(&mut self) -> bool {
+        let present = Path::new(&self.repository.path).exists();
+        let mut state = self.global_state.lock().await;
+        
+        if present {
+            if self.missing_polls > 0 {
+                self.log("repository_restored", "📂 Repository path is back; resuming monitoring");
+                if self.disable_missing_after.is_some_and(|limit| self.missing_polls >= limit) {
+                    state.set_repository_enabled(&self.repository.id, true);
+                }
+                state.update_repository_status(&self.repository.id, "Idle".to_string());
+                self.missing_polls = 0;
+            }
+            return true;
+        }
+        
+        // Nothing can be built, so don't let a pending request wake the next poll early
+        state.take_build_request(&self.repository.id);
+        self.missing_polls += 1;
+        if self.missing_polls == 1 {
+            logging::warn(Some(&self.repository.name), "repository_missing", &format!("Repository path no longer exists: {}", self.repository.path));
+            state.update_repository_status(&self.repository.id, "Missing".to_string());
+        }
+        if self.disable_missing_after == Some(self.missing_polls) {
+            logging::warn(Some(&self.repository.name), "repository_disabled", &format!(
+                "Disabling repository after {} polls with a missing path", self.missing_polls
+            ));
+            state.set_repository_enabled(&self.repository.id, false);
+            state.record_event("repository_disabled", Some(&self.repository.name), &format!(
+                "Disabled after {} polls with a missing path", self.missing_polls
+            ));
+        }
+        false
+    }
+    
+    // Sleeps until the next poll, waking early if a build is requested. Requests
+    // the next poll would only defer (everything while the daemon is paused,
+    // webhooks outside active hours) stay pending without waking it.
+    async fn wait_for_next_poll(&self) {
+        let mut ticks = tokio::time::interval(Duration::from_secs(1));
+        // The first tick completes immediately
+        ticks.tick().await;
+        for _ in 0..30 {
+            ticks.tick().await;
+            
+            let requested = {
+                let state = self.global_state.lock().await;
+                !state.paused && state.repositories
+                    .get(&self.repository.id)
+                    .is_some_and(|rs| {
+                        rs.build_requested
+                            || !rs.rerun_requests.is_empty()
+                            || (rs.webhook_received && !self.paused)
+                    })
+            };
+            if requested {
+                return;
+            }
+        }
+    }
+}
+
+// Retried attempts get their own section so the final one keeps the plain header
+fn append_command_output(all_output: &mut String, spec: &CommandSpec, retried_attempt: Option<u32>, output: &CommandOutput) {
+    match retried_attempt {
+        Some(attempt) => all_output.push_str(&format!("=== {} (attempt {}) ===\n", spec.name, attempt)),
+        None => all_output.push_str(&format!("=== {} ===\n", spec.name)),
+    }
+    if spec.name != spec.cmd {
+        all_output.push_str(&format!("$ {}\n", spec.cmd));
+    }
+    all_output.push_str(&output.stdout);
+    if !output.stderr.is_empty() {
+        all_output.push_str("STDERR:\n");
+        all_output.push_str(&output.stderr);
+    }
+}
+
+// Waiting steps with a dependency that failed or will never run can't run either
+fn mark_unreachable_steps(target: &BuildTarget, dependencies: &[Vec<usize>], states: &mut [StepState], notes: &Mutex<String>) {
+    loop {
+        let unreachable = (0..states.len()).find_map(|index| {
+            if states[index] != StepState::Waiting {
+                return None;
+            }
+            dependencies[index]
+                .iter()
+                .find(|&&dependency| matches!(states[dependency], StepState::Failed | StepState::NotRun))
+                .map(|&dependency| (index, dependency))
+        });
+        let Some((index, dependency)) = unreachable else {
+            return;
+        };
+        states[index] = StepState::NotRun;
+        let message = format!(
+            "Skipped {}: depends on '{}', which did not pass",
+            target.commands[index].name, target.commands[dependency].name
+        );
+        logging::info(Some(&target.label), "command_skipped", &format!("⏭️  {}", message));
+        notes.lock().unwrap().push_str(&format!("{}\n", message));
+    }
+}
+
+// Why a commit changing `changed` isn't built: with both lists set, a
+// matching trigger path is required and the ignored paths can still skip it
+fn path_filter_reason(trigger_paths: &[String], ignore_paths: &[String], changed: &[&str]) -> Option<String> {
+    let trigger_paths = compile_globs(trigger_paths);
+    let ignore_paths = compile_globs(ignore_paths);
+    if !trigger_paths.is_empty() && !changed.iter().any(|file| trigger_paths.iter().any(|glob| glob.is_match(file))) {
+        return Some("no changes under trigger_paths".to_string());
+    }
+    if !ignore_paths.is_empty() && changed.iter().all(|file| ignore_paths.iter().any(|glob| glob.is_match(file))) {
+        return Some("only ignored paths changed".to_string());
+    }
+    None
+}
+
+// Globs follow .gitignore conventions: `*` stays within a directory, `**` crosses
+// directories, and a pattern without a slash matches a file name at any depth
+fn compile_globs(patterns: &[String]) -> Vec<Regex> {
+    patterns
+        .iter()
+        .filter_map(|pattern| {
+            let mut regex = String::new();
+            let trimmed = pattern.trim_end_matches('/');
+            let mut chars = trimmed.trim_start_matches('/').chars().peekable();
+            while let Some(c) = chars.next() {
+                match c {
+                    '*' if chars.peek() == Some(&'*') => {
+                        chars.next();
+                        if chars.peek() == Some(&'/') {
+                            chars.next();
+                            regex.push_str("(.*/)?");
+                        } else {
+                            regex.push_str(".*");
+                        }
+                    }
+                    '*' => regex.push_str("[^/]*"),
+                    '?' => regex.push_str("[^/]"),
+                    c => regex.push_str(&regex::escape(&c.to_string())),
+                }
+            }
+            let anchor = if trimmed.contains('/') { "^" } else { "(^|/)" };
+            // A directory pattern also covers everything inside it
+            Regex::new(&format!("{}{}(/.*)?$", anchor, regex)).ok()
+        })
+        .collect()
+}
+
+// Commands such as `./scripts/ci.sh` only fail with an opaque shell error when
+// the script is missing or not executable, so check for those cases directly.
+fn describe_script_problem(cmd: &str, work_dir: &Path) -> Option<String> {
+    let program = cmd.split_whitespace().next()?;
+    if !program.contains('/') {
+        return None;
+    }
+    
+    let script_path = work_dir.join(program);
+    if !script_path.exists() {
+        return Some(format!(
+            "Script not found: {} (resolved to {})",
+            program,
+            script_path.display()
+        ));
+    }
+    
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        
+        if let Ok(metadata) = script_path.metadata()
+            && metadata.is_file()
+            && metadata.permissions().mode() & 0o111 == 0
+        {
+            return Some(format!(
+                "Script is not executable: {} (try `chmod +x {}`)",
+                program, program
+            ));
+        }
+    }
+    
+    None
+}
+
+// Whether a failed command's program is missing, as opposed to the program
+// running and failing: the shell's "command not found" status, plus the way
+// cargo and `python -m` report an uninstalled subcommand or module.
+fn is_command_missing(output: &CommandOutput) -> bool {
+    const SHELL_NOT_FOUND: i32 = if cfg!(target_os = "windows") { 9009 } else { 127 };
+    // With merged output, the shell's complaint is in stdout
+    let stderr = if output.stderr.is_empty() { output.stdout.trim() } else { output.stderr.trim() };
+    output.exit_code == Some(SHELL_NOT_FOUND)
+        || stderr.starts_with("error: no such command:")
+        || (stderr.lines().count() == 1 && stderr.contains(": No module named "))
+}
+
+fn deadline_passed(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+fn describe_exit_code(exit_code: Option<i32>) -> String {
+    match exit_code {
+        Some(code) => format!("code {}", code),
+        None => "no exit code (killed by a signal)".to_string(),
+    }
+}
+
+pub async fn commit_exists(git: &str, repo_path: &str, commit_hash: &str) -> bool {
+    Command::new(git)
+        .args(["cat-file", "-e", &format!("{}^{{commit}}", commit_hash)])
+        .current_dir(repo_path)
+        .output()
+        .await
+        .is_ok_and(|output| output.status.success())
+}
+
+fn decode_output(bytes: &[u8], encoding: OutputEncoding) -> String {
+    match encoding {
+        OutputEncoding::Utf8 => String::from_utf8_lossy(bytes).to_string(),
+        OutputEncoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
+        OutputEncoding::Base64 => {
+            let mut encoded = base64::engine::general_purpose::STANDARD.encode(bytes);
+            if !encoded.is_empty() {
+                encoded.push('\n');
+            }
+            encoded
+        }
+    }
+}
+
+fn read_pipe<R: AsyncRead + Unpin + Send + 'static>(pipe: Option<R>) -> JoinHandle<Vec<u8>> {
+    tokio::spawn(async move {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            pipe.read_to_end(&mut buffer).await.ok();
+        }
+        buffer
+    })
+}
+
+// Points the command's stdout and stderr at one pipe, so their lines keep
+// the order they were written in, and starts reading it
+#[cfg(unix)]
+fn merge_output(command: &mut Command) -> std::io::Result<JoinHandle<Vec<u8>>> {
+    let (writer, reader) = tokio::net::unix::pipe::pipe()?;
+    let writer = writer.into_blocking_fd()?;
+    command.stdout(writer.try_clone()?).stderr(writer);
+    Ok(read_pipe(Some(reader)))
+}
+
+#[cfg(not(unix))]
+fn merge_output(command: &mut Command) -> std::io::Result<JoinHandle<Vec<u8>>> {
+    use std::io::Read;
+    
+    let (mut reader, writer) = std::io::pipe()?;
+    command.stdout(writer.try_clone()?).stderr(writer);
+    Ok(tokio::task::spawn_blocking(move || {
+        let mut buffer = Vec::new();
+        reader.read_to_end(&mut buffer).ok();
+        buffer
+    }))
+}
+
+// Written from its own task so a command that produces output before reading
+// its input can't deadlock against us. Dropping the pipe closes stdin.
+fn write_stdin(mut pipe: ChildStdin, input: String) {
+    tokio::spawn(async move {
+        // A command that exits without reading everything closes the pipe early
+        pipe.write_all(input.as_bytes()).await.ok();
+    });
+}
+
+// Waits for the child, killing it once `timeout` elapses or `cancel` is set.
+// Returns whether it timed out and, where the platform reports it, the
+// resources it used.
+async fn wait_with_timeout(child: &mut Child, timeout: Option<Duration>, cancel: Option<&AtomicBool>) -> std::io::Result<(ExitStatus, bool, Option<ResourceUsage>)> {
+    let start = Instant::now();
+    let mut ticks = tokio::time::interval(EXIT_CHECK_INTERVAL);
+    let mut timed_out = false;
+    let mut killed = false;
+    loop {
+        ticks.tick().await;
+        if let Some(usage) = check_exited(child)? {
+            let status = child.wait().await?;
+            return Ok((status, timed_out, usage));
+        }
+        if killed {
+            continue;
+        }
+        timed_out = timeout.is_some_and(|timeout| start.elapsed() >= timeout);
+        if timed_out || cancel.is_some_and(|cancel| cancel.load(Ordering::SeqCst)) {
+            kill_process_tree(child);
+            killed = true;
+        }
+    }
+}
+
+// Whether the child has exited, and if so the resources it used. The child
+// is left for `Child::wait` to reap: waitid with WNOWAIT reports the usage
+// of this child alone, where getrusage(RUSAGE_CHILDREN) would mix in commands
+// other runners reaped at the same time.
+#[cfg(target_os = "linux")]
+fn check_exited(child: &mut Child) -> std::io::Result<Option<Option<ResourceUsage>>> {
+    let Some(pid) = child.id() else {
+        // Already reaped
+        return Ok(Some(None));
+    };
+    let mut info: libc::siginfo_t = unsafe { std::mem::zeroed() };
+    let mut rusage: libc::rusage = unsafe { std::mem::zeroed() };
+    loop {
+        // The libc wrapper has no rusage argument, but the system call does
+        let rc = unsafe {
+            libc::syscall(
+                libc::SYS_waitid,
+                libc::P_PID,
+                pid,
+                &mut info as *mut libc::siginfo_t,
+                libc::WEXITED | libc::WNOHANG | libc::WNOWAIT,
+                &mut rusage as *mut libc::rusage,
+            )
+        };
+        if rc < 0 {
+            let err = std::io::Error::last_os_error();
+            if err.kind() == std::io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+        // WNOHANG leaves si_pid zero while the child is still running
+        if unsafe { info.si_pid() } == 0 {
+            return Ok(None);
+        }
+        
+        let cpu_time = |time: libc::timeval| time.tv_sec.max(0) as u64 * 1000 + time.tv_usec.max(0) as u64 / 1000;
+        return Ok(Some(Some(ResourceUsage {
+            // ru_maxrss is in kilobytes on Linux
+            peak_memory_kb: rusage.ru_maxrss.max(0) as u64,
+            cpu_time_ms: cpu_time(rusage.ru_utime) + cpu_time(rusage.ru_stime),
+        })));
+    }
+}
+
+// Elsewhere checking reaps the child, and its resource usage goes unreported
+#[cfg(not(target_os = "linux"))]
+fn check_exited(child: &mut Child) -> std::io::Result<Option<Option<ResourceUsage>>> {
+    Ok(child.try_wait()?.map(|_| None))
+}
+
+/// Ids and home directory of a Unix user
+#[cfg(unix)]
+pub struct UserAccount {
+    pub uid: u32,
+    pub gid: u32,
+    pub home: PathBuf,
+}
+
+#[cfg(unix)]
+pub fn lookup_user(name: &str) -> Result<UserAccount, String> {
+    use std::ffi::{CStr, CString};
+    
+    let c_name = CString::new(name).map_err(|_| format!("invalid user name '{}'", name))?;
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut buffer = vec![0 as libc::c_char; 16 * 1024];
+    let mut found = std::ptr::null_mut();
+    let rc = unsafe { libc::getpwnam_r(c_name.as_ptr(), &mut passwd, buffer.as_mut_ptr(), buffer.len(), &mut found) };
+    if rc != 0 {
+        return Err(format!("cannot look up user '{}': {}", name, std::io::Error::from_raw_os_error(rc)));
+    }
+    if found.is_null() {
+        return Err(format!("user '{}' does not exist", name));
+    }
+    
+    let home = unsafe { CStr::from_ptr(passwd.pw_dir) }.to_string_lossy().to_string();
+    Ok(UserAccount {
+        uid: passwd.pw_uid,
+        gid: passwd.pw_gid,
+        home: PathBuf::from(home),
+    })
+}
+
+fn kill_process_tree(child: &mut Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        // The child leads its own process group, so this reaches grandchildren too
+        unsafe {
+            libc::kill(-(pid as i32), libc::SIGKILL);
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        child.start_kill().ok();
+    }
+}
+
+// Removes ANSI escape sequences (CSI colors/cursor moves, OSC titles/links and
+// two-byte escapes) so tool output renders cleanly in the dashboard.
+fn strip_ansi_codes(input: &str) -> String {
+    let mut result = String::with_capacity(input.len());
+    let mut chars = input.chars().peekable();
+    
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            result.push(c);
+            continue;
+        }
+        
+        match chars.peek() {
+            Some('[') => {
+                chars.next();
+                // CSI: parameters and intermediates until a final byte in @..~
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            Some(']') => {
+                chars.next();
+                // OSC: terminated by BEL or ESC \
+                while let Some(c) = chars.next() {
+                    if c == '\u{7}' {
+                        break;
+                    }
+                    if c == '\u{1b}' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            Some(_) => {
+                chars.next();
+            }
+            None => {}
+        }
+    }
+    
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A scratch directory removed again when the test finishes
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let path = std::env::temp_dir().join(format!("turbulent-ci-test-{}", uuid::Uuid::new_v4()));
+            fs::create_dir_all(&path).unwrap();
+            TempDir(path)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn describes_missing_script() {
+        let dir = TempDir::new();
+        let problem = describe_script_problem("./script.sh --verbose", &dir.0).unwrap();
+        assert_eq!(
+            problem,
+            format!("Script not found: ./script.sh (resolved to {})", dir.0.join("./script.sh").display())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn describes_non_executable_script() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new();
+        let script = dir.0.join("script.sh");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o644)).unwrap();
+        assert_eq!(
+            describe_script_problem("./script.sh", &dir.0).as_deref(),
+            Some("Script is not executable: ./script.sh (try `chmod +x ./script.sh`)")
+        );
+
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(describe_script_problem("./script.sh", &dir.0), None);
+    }
+
+    fn globs(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn trigger_paths_require_a_matching_change() {
+        let trigger = globs(&["src/**"]);
+        assert_eq!(path_filter_reason(&trigger, &[], &["src/main.rs", "README.md"]), None);
+        assert_eq!(
+            path_filter_reason(&trigger, &[], &["docs/guide.md"]).as_deref(),
+            Some("no changes under trigger_paths")
+        );
+    }
+
+    #[test]
+    fn ignore_paths_skip_when_every_change_matches() {
+        let ignore = globs(&["*.md", "docs"]);
+        assert_eq!(
+            path_filter_reason(&[], &ignore, &["README.md", "docs/img/logo.png"]).as_deref(),
+            Some("only ignored paths changed")
+        );
+        assert_eq!(path_filter_reason(&[], &ignore, &["README.md", "src/lib.rs"]), None);
+    }
+
+    #[test]
+    fn both_filters_skip_docs_only_changes() {
+        let (trigger, ignore) = (globs(&["src/**"]), globs(&["*.md"]));
+        assert!(path_filter_reason(&trigger, &ignore, &["docs/guide.md"]).is_some());
+        // The README example: under trigger_paths, but every change is ignored
+        assert_eq!(
+            path_filter_reason(&trigger, &ignore, &["src/README.md"]).as_deref(),
+            Some("only ignored paths changed")
+        );
+    }
+
+    #[test]
+    fn both_filters_build_source_changes() {
+        let (trigger, ignore) = (globs(&["src/**"]), globs(&["*.md"]));
+        assert_eq!(path_filter_reason(&trigger, &ignore, &["src/main.rs", "CHANGELOG.md"]), None);
+    }
+
+    #[test]
+    fn ignores_commands_on_path() {
+        let dir = TempDir::new();
+        assert_eq!(describe_script_problem("cargo test", &dir.0), None);
+    }
+
+    #[test]
+    fn failure_skips_transitive_dependents() {
+        let target = BuildTarget {
+            label: "app".to_string(),
+            subproject: None,
+            root: PathBuf::from("."),
+            rerun: false,
+            work_dir: PathBuf::from("."),
+            project_type: ProjectType::Generic,
+            commands: ["build", "test", "package", "lint"].map(|name| CommandSpec::named(name, "true")).to_vec(),
+        };
+        // package needs test, which needs build; lint stands alone
+        let dependencies = vec![vec![], vec![0], vec![1], vec![]];
+        let mut states = vec![StepState::Failed, StepState::Waiting, StepState::Waiting, StepState::Waiting];
+        let notes = Mutex::new(String::new());
+
+        mark_unreachable_steps(&target, &dependencies, &mut states, &notes);
+
+        assert_eq!(states, [StepState::Failed, StepState::NotRun, StepState::NotRun, StepState::Waiting]);
+        assert_eq!(
+            notes.into_inner().unwrap(),
+            "Skipped test: depends on 'build', which did not pass\nSkipped package: depends on 'test', which did not pass\n"
+        );
     }
 }
@@ -1,9 +1,18 @@
+use crate::config::ProjectType;
+use crate::logging::LogFormat;
 use clap::{Parser, Subcommand};
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "turbulent-ci")]
 #[command(about = "A turbulent CI system for multiple repositories")]
 pub struct Cli {
+    /// Directory holding repositories.json, settings.json and daemon state
+    #[arg(long, global = true, env = "TURBULENT_CONFIG_DIR")]
+    pub config_dir: Option<PathBuf>,
+    /// Named set of repositories, settings and state kept apart from the others
+    #[arg(long, global = true, env = "TURBULENT_PROFILE", value_parser = parse_profile)]
+    pub profile: Option<String>,
     #[command(subcommand)]
     pub command: Commands,
 }
@@ -18,14 +27,40 @@ pub enum Commands {
         /// Configuration file path
         #[arg(short, long)]
         config_file: Option<String>,
+        /// Log output format
+        #[arg(long, value_enum, default_value = "human")]
+        log_format: LogFormat,
+        /// Refuse to start if any repository fails validation
+        #[arg(long)]
+        strict: bool,
+        /// Try the next ports if the requested one is in use
+        #[arg(long)]
+        auto_port: bool,
+        /// Start even with more repositories than max_repositories
+        #[arg(long)]
+        force: bool,
+        /// Rebuild each repository's HEAD even if it was built before the restart
+        #[arg(long)]
+        rebuild_on_start: bool,
     },
     /// Add a repository to monitor
     Add {
-        /// Repository path
+        /// Repository path, or a remote URL when --clone-to is given
         path: String,
         /// Repository name (optional)
         #[arg(short, long)]
         name: Option<String>,
+        /// Force the project type instead of detecting it
+        #[arg(short = 't', long = "type", value_enum)]
+        project_type: Option<ProjectType>,
+        /// Clone the remote URL into this directory and monitor the clone
+        #[arg(long)]
+        clone_to: Option<String>,
+    },
+    /// Show how a directory's project type would be detected
+    Detect {
+        /// Directory to inspect
+        path: String,
     },
     /// Remove a repository from monitoring
     Remove {
@@ -34,6 +69,94 @@ pub enum Commands {
     },
     /// List all configured repositories
     List,
+    /// Export all repositories to a file
+    Export {
+        /// Destination file
+        file: String,
+    },
+    /// Merge repositories from an exported file
+    Import {
+        /// Exported file
+        file: String,
+        /// Re-root each repository's directory under this path
+        #[arg(long)]
+        base_path: Option<String>,
+    },
+    /// Move a repository to a new display position
+    Reorder {
+        /// Repository name
+        name: String,
+        /// New position, starting at 1
+        position: usize,
+    },
+    /// Show a repository's configuration and live status
+    Show {
+        /// Repository name
+        name: String,
+        /// Print machine-readable JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// Check daemon status
     Status,
+    /// Trigger a build on the running daemon
+    Trigger {
+        /// Repository name
+        #[arg(required_unless_present = "all", conflicts_with = "all")]
+        name: Option<String>,
+        /// Trigger every enabled repository
+        #[arg(long)]
+        all: bool,
+        /// Block until the build finishes, exiting non-zero if it failed
+        #[arg(long, conflicts_with = "all")]
+        wait: bool,
+        /// Give up waiting after this many seconds (exit code 2)
+        #[arg(long, requires = "wait")]
+        timeout: Option<u64>,
+    },
+    /// Acknowledge a failing repository, leaving it out of the failing count
+    /// until its next successful build
+    Ack {
+        /// Repository name
+        name: String,
+    },
+    /// Defer all builds on the running daemon until `resume`
+    Pause,
+    /// Resume building after `pause`, starting with each repository's latest commit
+    Resume,
+    /// Re-run a past build against its original commit
+    Rebuild {
+        /// Build id
+        build_id: u64,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Print the fully resolved configuration the daemon would use
+    Show {
+        /// Output format
+        #[arg(long, value_enum, default_value = "json")]
+        format: ConfigFormat,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ConfigFormat {
+    Json,
+    Toml,
+}
+
+// Profiles name a directory, so keep them to a single safe path component
+fn parse_profile(value: &str) -> Result<String, String> {
+    if !value.is_empty() && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        Ok(value.to_string())
+    } else {
+        Err("profile names may only contain letters, digits, '-' and '_'".to_string())
+    }
 }
@@ -1,22 +1,204 @@
+use crate::logging;
 use crate::project_detector::ProjectDetector;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use std::time::Duration;
 use uuid::Uuid;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    #[serde(skip)]
     pub web_port: u16,
+    #[serde(skip)]
     pub config_file: String,
+    /// Fall back to the next free port when `web_port` is taken
+    #[serde(skip)]
+    pub auto_port: bool,
+    /// Build every repository's HEAD on startup, ignoring the commits the
+    /// previous run already built
+    #[serde(skip)]
+    pub rebuild_on_start: bool,
+    #[serde(skip)]
     #[allow(dead_code)]
     pub poll_interval: Duration,
+    /// Regexes whose matches are replaced in captured build output
+    #[serde(default)]
+    pub redact_patterns: Vec<String>,
+    /// Named notification targets that repositories can reference
+    #[serde(default)]
+    pub notifiers: Vec<NotifierConfig>,
+    /// Named command lists that repositories can reference via `template`
+    #[serde(default)]
+    pub templates: HashMap<String, Vec<CommandSpec>>,
+    /// Groups of repositories reported together, e.g. the repos of one service
+    #[serde(default)]
+    pub projects: Vec<Project>,
+    /// How often the dashboard reloads data
+    #[serde(default = "default_refresh_secs")]
+    pub dashboard_refresh_secs: u64,
+    /// Commit hash characters the dashboard shows
+    #[serde(default = "default_commit_hash_length")]
+    pub commit_hash_length: usize,
+    /// Lines of the failing command's output included in failure notifications
+    #[serde(default = "default_failure_log_tail_lines")]
+    pub failure_log_tail_lines: usize,
+    /// Minimum time between failure notifications for one repository;
+    /// failures in between are summarised in the next notification
+    #[serde(default)]
+    pub notification_cooldown_secs: Option<u64>,
+    /// Commit message markers that suppress commit-triggered builds
+    #[serde(default = "default_skip_markers")]
+    pub skip_markers: Vec<String>,
+    /// Disable a repository after this many polls find its path missing
+    #[serde(default)]
+    pub disable_missing_after: Option<u32>,
+    /// Git executable; defaults to `git` on PATH
+    #[serde(default)]
+    pub git_path: Option<String>,
+    /// Where build history is kept
+    #[serde(default)]
+    pub storage: StorageKind,
+    /// SQLite database file; defaults to builds.db next to the repository list
+    #[serde(default)]
+    pub database_path: Option<String>,
+    #[serde(default)]
+    pub retention: Retention,
+    /// Age in days past which builds are pruned with `retention: "by-age"`
+    #[serde(default = "default_retention_days")]
+    pub retention_days: u64,
+    /// Delay between starting each repository's runner
+    #[serde(default)]
+    pub startup_stagger_secs: u64,
+    /// Worker cap for repositories running commands in parallel
+    #[serde(default = "default_max_parallel_commands")]
+    pub max_parallel_commands: usize,
+    /// Builds running at once across repositories; unlimited when unset
+    #[serde(default)]
+    pub max_concurrent_builds: Option<usize>,
+    /// Separate caps for some project types, which then don't count
+    /// towards `max_concurrent_builds`
+    #[serde(default)]
+    pub max_concurrent_builds_by_type: HashMap<ProjectType, usize>,
+    /// Also notify when the daemon starts and stops
+    #[serde(default)]
+    pub notify_lifecycle: bool,
+    /// Keep the event log in the state directory so it survives restarts
+    #[serde(default)]
+    pub persist_events: bool,
+    /// Builds taking this many times their baseline duration are flagged as slow
+    #[serde(default = "default_slow_build_factor")]
+    pub slow_build_factor: f64,
+    /// More repositories than this need `start --force`, as each polls git on its own
+    #[serde(default = "default_max_repositories")]
+    pub max_repositories: usize,
+    /// Unix user that build commands run as, for repositories without their own
+    #[serde(default)]
+    pub run_as_user: Option<String>,
+    /// Unix scheduling priority of build commands, for repositories without their own
+    #[serde(default)]
+    pub nice_level: Option<i32>,
 }
 
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+fn default_failure_log_tail_lines() -> usize {
+    20
+}
+
+fn default_skip_markers() -> Vec<String> {
+    vec!["[skip ci]".to_string(), "[ci skip]".to_string()]
+}
+
+fn default_max_parallel_commands() -> usize {
+    4
+}
+
+fn default_max_repositories() -> usize {
+    64
+}
+
+fn default_slow_build_factor() -> f64 {
+    1.5
+}
+
+fn default_refresh_secs() -> u64 {
+    15
+}
+
+fn default_commit_hash_length() -> usize {
+    8
+}
+
+fn default_retention_days() -> u64 {
+    30
+}
+
+/// How long finished builds are kept
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Retention {
+    /// The last 50 builds per repository and 100 overall
+    #[default]
+    Count,
+    /// Builds newer than `retention_days`, however many there are
+    ByAge,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageKind {
+    /// Recent builds only, lost on restart
+    #[default]
+    Memory,
+    /// Every build in a SQLite database (requires the `sqlite` feature)
+    Sqlite,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotifierKind {
+    #[default]
+    Webhook,
+    Slack,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotifierConfig {
+    pub name: String,
+    #[serde(default)]
+    pub kind: NotifierKind,
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Project {
+    pub name: String,
+    /// Member repositories, by id or name
+    pub repositories: Vec<String>,
+}
+
+impl Project {
+    pub fn contains(&self, repository: &Repository) -> bool {
+        self.repositories
+            .iter()
+            .any(|member| *member == repository.name || *member == repository.id.to_string())
+    }
+}
+
+/// Extra notifiers fired once a repository fails this many builds in a row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationStep {
+    pub after_failures: u32,
+    pub notifiers: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize, clap::ValueEnum)]
 pub enum ProjectType {
     Rust,
     Python,
     Node,
+    Deno,
+    Bun,
     Generic,
 }
 
@@ -26,38 +208,665 @@ pub struct Repository {
     pub name: String,
     pub path: String,
     pub project_type: ProjectType,
-    pub commands: Vec<String>,
+    pub commands: Vec<CommandSpec>,
     pub enabled: bool,
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    #[serde(default)]
+    pub notifiers: Vec<String>,
+    #[serde(default = "default_true")]
+    pub strip_ansi: bool,
+    #[serde(default)]
+    pub subprojects: Vec<SubProject>,
+    /// Stop at the first failing command instead of running them all
+    #[serde(default = "default_true")]
+    pub fail_fast: bool,
+    /// Per-command timeout in seconds
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Wall-clock limit for all commands of a build together
+    #[serde(default)]
+    pub build_timeout_secs: Option<u64>,
+    /// Set for managed clones of remote repositories, which are fetched every poll
+    #[serde(default)]
+    pub remote_url: Option<String>,
+    /// Shared secret for push webhooks to `api/webhook/<name>`; webhooks
+    /// are refused without one
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    /// URL sent the build result JSON after each passing build, e.g. to deploy
+    #[serde(default)]
+    pub on_success_webhook: Option<String>,
+    /// URL sent the build result JSON after each failing build
+    #[serde(default)]
+    pub on_failure_webhook: Option<String>,
+    /// Display position; ties are broken by name
+    #[serde(default)]
+    pub order: u32,
+    /// Cron expression for builds independent of commits, e.g. "0 3 * * *"
+    #[serde(default)]
+    pub schedule: Option<String>,
+    #[serde(default)]
+    pub output_encoding: OutputEncoding,
+    #[serde(default)]
+    pub output_mode: OutputMode,
+    /// Name of a command template from settings.json that replaces `commands`
+    #[serde(default)]
+    pub template: Option<String>,
+    /// Commands appended after the template's
+    #[serde(default)]
+    pub extra_commands: Vec<CommandSpec>,
+    /// Run after every build, even a failed or timed out one, without
+    /// affecting its result
+    #[serde(default)]
+    pub cleanup_commands: Vec<String>,
+    /// Commands such as `rustc --version` whose output is recorded with
+    /// each build, to know which toolchain it ran against
+    #[serde(default)]
+    pub capture_versions: Vec<String>,
+    /// Failed builds in a row before the status shows Failed instead of Unstable
+    #[serde(default = "default_consecutive_failures_to_fail")]
+    pub consecutive_failures_to_fail: u32,
+    /// Extra attempts for a failing command
+    #[serde(default)]
+    pub retries: u32,
+    /// Exit codes worth retrying; empty retries any failure
+    #[serde(default)]
+    pub retry_on_exit_codes: Vec<i32>,
+    /// Run commands concurrently instead of one after another
+    #[serde(default)]
+    pub parallel: bool,
+    /// Overrides the global worker cap for parallel commands
+    #[serde(default)]
+    pub max_parallel_commands: Option<usize>,
+    /// Globs; a new commit is only built if it changes a matching file
+    #[serde(default)]
+    pub trigger_paths: Vec<String>,
+    /// Globs; a new commit that only changes matching files is not built
+    #[serde(default)]
+    pub ignore_paths: Vec<String>,
+    /// Commit authors whose commits are built, or not built
+    #[serde(default)]
+    pub author_filter: Option<AuthorFilter>,
+    /// What identifies an already-built revision
+    #[serde(default)]
+    pub dedup_by: DedupBy,
+    /// Local-time window for polling and building, e.g. "18:00-06:00"
+    #[serde(default)]
+    pub active_hours: Option<String>,
+    /// Run `git submodule update` before building; unset means whenever
+    /// the repository has a `.gitmodules` file
+    #[serde(default)]
+    pub update_submodules: Option<bool>,
+    /// Cancel a running build when a newer commit is checked out
+    #[serde(default)]
+    pub supersede_on_new_commit: bool,
+    /// Build in a throwaway `git worktree` of the commit instead of the
+    /// working tree itself
+    #[serde(default)]
+    pub isolated: bool,
+    #[serde(default)]
+    pub escalation: Vec<EscalationStep>,
+    /// Unix user that build commands run as
+    #[serde(default)]
+    pub run_as_user: Option<String>,
+    /// Unix scheduling priority of build commands, from -20 (highest) to 19
+    #[serde(default)]
+    pub nice_level: Option<i32>,
+    /// Re-detect the project type on every new commit
+    #[serde(default)]
+    pub auto_redetect: bool,
+}
+
+/// Author names or emails, matched case-insensitively with `*` wildcards,
+/// e.g. `{"deny": ["dependabot[bot]", "*@bots.example.com"]}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthorFilter {
+    /// Only commits by a matching author are built
+    Allow(Vec<String>),
+    /// Commits by a matching author are not built
+    Deny(Vec<String>),
+}
+
+impl AuthorFilter {
+    /// Why a commit by this author shouldn't be built, if it shouldn't
+    pub fn rejects(&self, name: &str, email: &str) -> Option<String> {
+        let matches = |patterns: &[String]| patterns
+            .iter()
+            .any(|pattern| wildcard_match(pattern, name) || wildcard_match(pattern, email));
+        match self {
+            AuthorFilter::Allow(patterns) if !matches(patterns) => {
+                Some(format!("author {} <{}> is not in author_filter's allow list", name, email))
+            }
+            AuthorFilter::Deny(patterns) if matches(patterns) => {
+                Some(format!("author {} <{}> is in author_filter's deny list", name, email))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn wildcard_match(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let value = value.to_lowercase();
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        // No wildcard at all
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+/// Key used to skip rebuilding content that was already built
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupBy {
+    /// Rebuild whenever HEAD points at a different commit
+    #[default]
+    Commit,
+    /// Rebuild only when the tree differs, so e.g. a revert of a revert is skipped
+    Tree,
+}
+
+/// How a command's stdout and stderr end up in the build output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    /// Stdout, followed by stderr under a `STDERR:` marker
+    #[default]
+    Sequential,
+    /// Both through one pipe, interleaved in the order they were written
+    Merged,
+    /// As `sequential`, with each step also keeping its stdout and stderr apart
+    Separate,
+}
+
+/// How captured stdout/stderr bytes are turned into text
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputEncoding {
+    /// UTF-8, replacing invalid sequences
+    #[default]
+    Utf8,
+    /// ISO-8859-1, which maps every byte to a character
+    Latin1,
+    /// Raw bytes preserved as base64
+    Base64,
+}
+
+impl OutputEncoding {
+    pub fn label(&self) -> &'static str {
+        match self {
+            OutputEncoding::Utf8 => "utf-8",
+            OutputEncoding::Latin1 => "latin-1",
+            OutputEncoding::Base64 => "base64",
+        }
+    }
+}
+
+/// A build step. Plain strings are accepted and used as both label and command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "CommandDef")]
+pub struct CommandSpec {
+    pub name: String,
+    pub cmd: String,
+    /// Names of steps that must succeed before this one starts
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Skip instead of failing when the command's program isn't installed
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub optional: bool,
+    /// Text written to the command's standard input, which is then closed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stdin: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CommandDef {
+    Plain(String),
+    Full {
+        name: Option<String>,
+        cmd: String,
+        #[serde(default)]
+        depends_on: Vec<String>,
+        #[serde(default)]
+        optional: bool,
+        #[serde(default)]
+        stdin: Option<String>,
+    },
+}
+
+impl From<CommandDef> for CommandSpec {
+    fn from(def: CommandDef) -> Self {
+        match def {
+            CommandDef::Plain(cmd) => Self::new(cmd),
+            CommandDef::Full { name, cmd, depends_on, optional, stdin } => Self {
+                name: name.unwrap_or_else(|| cmd.clone()),
+                cmd,
+                depends_on,
+                optional,
+                stdin,
+            },
+        }
+    }
+}
+
+impl CommandSpec {
+    pub fn new(cmd: impl Into<String>) -> Self {
+        let cmd = cmd.into();
+        Self { name: cmd.clone(), cmd, depends_on: Vec::new(), optional: false, stdin: None }
+    }
+    
+    pub fn named(name: impl Into<String>, cmd: impl Into<String>) -> Self {
+        Self { name: name.into(), cmd: cmd.into(), depends_on: Vec::new(), optional: false, stdin: None }
+    }
+    
+    pub fn skip_if_missing(mut self) -> Self {
+        self.optional = true;
+        self
+    }
+}
+
+/// Checks that `depends_on` forms a graph that can be scheduled: every name
+/// refers to exactly one step and there are no cycles.
+pub fn validate_dependencies(commands: &[CommandSpec]) -> Result<(), String> {
+    let mut index_by_name = HashMap::new();
+    for (index, spec) in commands.iter().enumerate() {
+        if index_by_name.insert(spec.name.as_str(), index).is_some() {
+            return Err(format!("Step name '{}' is used more than once, so depends_on cannot refer to it", spec.name));
+        }
+    }
+    
+    let mut dependents = vec![Vec::new(); commands.len()];
+    let mut waiting_on = vec![0; commands.len()];
+    for (index, spec) in commands.iter().enumerate() {
+        for dependency in &spec.depends_on {
+            let Some(&dependency_index) = index_by_name.get(dependency.as_str()) else {
+                return Err(format!("Step '{}' depends on unknown step '{}'", spec.name, dependency));
+            };
+            dependents[dependency_index].push(index);
+            waiting_on[index] += 1;
+        }
+    }
+    
+    let mut ready: Vec<usize> = (0..commands.len()).filter(|&index| waiting_on[index] == 0).collect();
+    let mut scheduled = 0;
+    while let Some(index) = ready.pop() {
+        scheduled += 1;
+        for &dependent in &dependents[index] {
+            waiting_on[dependent] -= 1;
+            if waiting_on[dependent] == 0 {
+                ready.push(dependent);
+            }
+        }
+    }
+    
+    if scheduled < commands.len() {
+        let stuck: Vec<&str> = (0..commands.len())
+            .filter(|&index| waiting_on[index] > 0)
+            .map(|index| commands[index].name.as_str())
+            .collect();
+        return Err(format!("Dependency cycle between steps: {}", stuck.join(", ")));
+    }
+    Ok(())
+}
+
+/// Daily window in local time; one whose end is before its start spans midnight
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActiveHours {
+    start: chrono::NaiveTime,
+    end: chrono::NaiveTime,
+}
+
+impl ActiveHours {
+    pub fn parse(window: &str) -> Result<Self, String> {
+        let (start, end) = window
+            .split_once('-')
+            .ok_or_else(|| format!("expected HH:MM-HH:MM, got '{}'", window))?;
+        let parse_time = |time: &str| {
+            chrono::NaiveTime::parse_from_str(time.trim(), "%H:%M")
+                .map_err(|_| format!("'{}' is not a HH:MM time", time.trim()))
+        };
+        let (start, end) = (parse_time(start)?, parse_time(end)?);
+        if start == end {
+            return Err(format!("'{}' starts and ends at the same time, so it is never active", window.trim()));
+        }
+        Ok(Self { start, end })
+    }
+    
+    pub fn contains(&self, time: chrono::NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+pub const REPO_CI_FILE: &str = ".turbulent-ci.toml";
+
+/// Shown in place of secrets in output that may be shared
+pub const MASKED: &str = "********";
+
+// Unix niceness, highest priority first
+const NICE_LEVELS: std::ops::RangeInclusive<i32> = -20..=19;
+
+/// CI definition carried in the repository itself, overriding the stored one
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RepoCiFile {
+    pub commands: Option<Vec<CommandSpec>>,
+    pub env: Option<HashMap<String, String>>,
+    pub timeout: Option<u64>,
+}
+
+impl RepoCiFile {
+    // Returns None when the file is absent; a malformed file is logged and ignored
+    pub fn load(repo_path: &str) -> Option<Self> {
+        let path = std::path::Path::new(repo_path).join(REPO_CI_FILE);
+        let content = std::fs::read_to_string(&path).ok()?;
+        
+        match toml::from_str(&content) {
+            Ok(file) => Some(file),
+            Err(e) => {
+                logging::warn(None, "malformed_repo_ci_file", &format!("Ignoring malformed {}: {}", path.display(), e));
+                None
+            }
+        }
+    }
+}
+
+/// An independently-built package inside a repository (e.g. in a monorepo)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubProject {
+    pub name: String,
+    /// Directory relative to the repository root
+    pub work_dir: String,
+    pub project_type: ProjectType,
+    /// Defaults for `project_type` are used when empty
+    #[serde(default)]
+    pub commands: Vec<CommandSpec>,
+}
+
+impl SubProject {
+    pub fn effective_commands(&self) -> Vec<CommandSpec> {
+        if self.commands.is_empty() {
+            Repository::get_default_commands(&self.project_type)
+        } else {
+            self.commands.clone()
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_consecutive_failures_to_fail() -> u32 {
+    1
+}
+
+static CONFIG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// Overrides the per-user configuration directory for this process
+pub fn set_config_dir(dir: PathBuf) {
+    CONFIG_DIR.set(dir).ok();
+}
+
+static PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Keeps this process's repositories, settings and state in a subdirectory
+/// of the configuration directory named after `profile`
+pub fn set_profile(profile: String) {
+    PROFILE.set(profile).ok();
+}
+
+pub fn profile() -> Option<&'static str> {
+    PROFILE.get().map(String::as_str)
+}
+
+pub fn config_dir() -> PathBuf {
+    let dir = CONFIG_DIR.get().cloned().unwrap_or_else(|| {
+        dirs::config_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("turbulent-ci")
+    });
+    match profile() {
+        Some(profile) => dir.join(profile),
+        None => dir,
+    }
+}
+
+pub fn settings_file() -> PathBuf {
+    config_dir().join("settings.json")
+}
+
+/// Where the running daemon records the address it serves on, so CLI
+/// commands reach the daemon of their own profile
+pub fn daemon_url_file() -> PathBuf {
+    config_dir().join("daemon_url")
 }
 
 impl Config {
     pub fn new(port: u16, config_file: Option<String>) -> Self {
-        let config_dir = dirs::config_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join("turbulent-ci");
+        let config_dir = config_dir();
         
+        // Failures surface through `ensure_writable` for commands that save
         std::fs::create_dir_all(&config_dir).ok();
         
         let config_file = config_file.unwrap_or_else(|| {
             config_dir.join("repositories.json").to_string_lossy().to_string()
         });
         
+        // Global settings live next to the repository list and are optional
+        let settings_file = settings_file();
+        let mut config = match std::fs::read_to_string(&settings_file) {
+            Ok(content) => serde_json::from_str::<Config>(&content).unwrap_or_else(|e| {
+                logging::warn(None, "invalid_settings", &format!("Failed to parse {}: {}", settings_file.display(), e));
+                Self::empty()
+            }),
+            Err(_) => Self::empty(),
+        };
+        
+        config.web_port = port;
+        config.config_file = config_file;
+        config.poll_interval = Duration::from_secs(30);
+        config
+    }
+    
+    fn empty() -> Self {
         Self {
-            web_port: port,
-            config_file,
-            poll_interval: Duration::from_secs(30),
+            web_port: 0,
+            config_file: String::new(),
+            auto_port: false,
+            rebuild_on_start: false,
+            poll_interval: Duration::from_secs(0),
+            redact_patterns: Vec::new(),
+            notifiers: Vec::new(),
+            templates: HashMap::new(),
+            projects: Vec::new(),
+            dashboard_refresh_secs: default_refresh_secs(),
+            commit_hash_length: default_commit_hash_length(),
+            failure_log_tail_lines: default_failure_log_tail_lines(),
+            notification_cooldown_secs: None,
+            skip_markers: default_skip_markers(),
+            disable_missing_after: None,
+            git_path: None,
+            storage: StorageKind::default(),
+            database_path: None,
+            retention: Retention::default(),
+            retention_days: default_retention_days(),
+            startup_stagger_secs: 0,
+            max_parallel_commands: default_max_parallel_commands(),
+            max_concurrent_builds: None,
+            max_concurrent_builds_by_type: HashMap::new(),
+            notify_lifecycle: false,
+            persist_events: false,
+            slow_build_factor: default_slow_build_factor(),
+            max_repositories: default_max_repositories(),
+            run_as_user: None,
+            nice_level: None,
+        }
+    }
+    
+    // Repositories without an explicit notifier list use every notifier
+    pub fn notifiers_for(&self, repository: &Repository) -> Vec<NotifierConfig> {
+        if repository.notifiers.is_empty() {
+            return self.notifiers.clone();
+        }
+        self.notifiers_named(&repository.notifiers)
+    }
+    
+    pub fn run_as_user_for(&self, repository: &Repository) -> Option<String> {
+        repository.run_as_user.clone().or_else(|| self.run_as_user.clone())
+    }
+    
+    pub fn nice_level_for(&self, repository: &Repository) -> Option<i32> {
+        repository.nice_level.or(self.nice_level)
+    }
+    
+    /// Age past which builds are pruned, or None to keep a fixed number
+    pub fn max_build_age(&self) -> Option<Duration> {
+        match self.retention {
+            Retention::Count => None,
+            Retention::ByAge => Some(Duration::from_secs(self.retention_days * 24 * 60 * 60)),
         }
     }
     
+    pub fn notifiers_named(&self, names: &[String]) -> Vec<NotifierConfig> {
+        self.notifiers
+            .iter()
+            .filter(|n| names.contains(&n.name))
+            .cloned()
+            .collect()
+    }
+    
+    // A template replaces the repository's commands, followed by its `extra_commands`
+    pub fn apply_template(&self, mut repository: Repository) -> Repository {
+        if let Some(ref name) = repository.template
+            && let Some(commands) = self.templates.get(name)
+        {
+            repository.commands = commands.iter().chain(&repository.extra_commands).cloned().collect();
+        }
+        repository
+    }
+    
+    pub fn validate_template_refs(&self, repositories: &[Repository]) -> Vec<String> {
+        repositories
+            .iter()
+            .filter_map(|repo| match repo.template {
+                Some(ref name) if !self.templates.contains_key(name) => Some(format!(
+                    "Repository '{}' references unknown template '{}'; using its own commands", repo.name, name
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+    
+    pub fn validate_notifier_refs(&self, repositories: &[Repository]) -> Vec<String> {
+        let mut problems = Vec::new();
+        for repo in repositories {
+            let escalated = repo.escalation.iter().flat_map(|step| &step.notifiers);
+            for name in repo.notifiers.iter().chain(escalated) {
+                if !self.notifiers.iter().any(|n| &n.name == name) {
+                    problems.push(format!("Repository '{}' references unknown notifier '{}'", repo.name, name));
+                }
+            }
+        }
+        problems
+    }
+    
+    pub fn validate_project_refs(&self, repositories: &[Repository]) -> Vec<String> {
+        let mut problems = Vec::new();
+        for project in &self.projects {
+            for member in &project.repositories {
+                if !repositories.iter().any(|repo| *member == repo.name || *member == repo.id.to_string()) {
+                    problems.push(format!("Project '{}' references unknown repository '{}'", project.name, member));
+                }
+            }
+        }
+        problems
+    }
+    
+    /// Checks that the directory holding the repository list can be written to
+    pub fn ensure_writable(&self) -> Result<(), String> {
+        let dir = Path::new(&self.config_file)
+            .parent()
+            .filter(|dir| !dir.as_os_str().is_empty())
+            .unwrap_or(Path::new("."));
+        let hint = "fix its permissions or choose another location with --config-dir or TURBULENT_CONFIG_DIR";
+        
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Cannot create config directory {}: {} ({})", dir.display(), e, hint))?;
+        
+        let probe = dir.join(".turbulent-ci-write-test");
+        std::fs::write(&probe, b"")
+            .map_err(|e| format!("Config directory {} is not writable: {} ({})", dir.display(), e, hint))?;
+        std::fs::remove_file(&probe).ok();
+        Ok(())
+    }
+    
+    pub fn git(&self) -> &str {
+        self.git_path.as_deref().unwrap_or("git")
+    }
+    
+    /// Runs `git --version` to confirm the configured executable works
+    pub fn check_git(&self) -> Result<String, String> {
+        let output = std::process::Command::new(self.git())
+            .arg("--version")
+            .output()
+            .map_err(|e| format!("Cannot run git executable '{}': {} (set git_path in settings.json)", self.git(), e))?;
+        if !output.status.success() {
+            return Err(format!("'{} --version' failed: {}", self.git(), String::from_utf8_lossy(&output.stderr).trim()));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+    
+    /// Masks notifier URLs, which usually embed a token
+    pub fn hide_secrets(&mut self) {
+        for notifier in &mut self.notifiers {
+            notifier.url = MASKED.to_string();
+        }
+    }
+    
+    pub fn database_file(&self) -> PathBuf {
+        match self.database_path {
+            Some(ref path) => PathBuf::from(path),
+            None => self.state_file("builds.db"),
+        }
+    }
+    
+    // Daemon state files are kept alongside the repository list
+    pub fn state_file(&self, name: &str) -> PathBuf {
+        Path::new(&self.config_file)
+            .parent()
+            .map(|dir| dir.join(name))
+            .unwrap_or_else(|| PathBuf::from(name))
+    }
+    
     pub fn default() -> Self {
         Self::new(3030, None)
     }
 }
 
 impl Repository {
-    pub fn new(path: String, name: Option<String>) -> Result<Self, Box<dyn std::error::Error>> {
-        let detector = ProjectDetector::new();
-        let project_type = detector.detect_project_type(&path);
+    pub fn new(path: String, name: Option<String>, project_type: Option<ProjectType>) -> Result<Self, Box<dyn std::error::Error>> {
+        // An explicit type bypasses detection entirely
+        let project_type = project_type.unwrap_or_else(|| {
+            ProjectDetector::new().detect_project_type(&path).project_type
+        });
         
         // Validate path exists
         if !std::path::Path::new(&path).exists() {
@@ -81,29 +890,328 @@ impl Repository {
             project_type,
             commands,
             enabled: true,
-        })
+            env: HashMap::new(),
+            notifiers: Vec::new(),
+            strip_ansi: true,
+            subprojects: Vec::new(),
+            fail_fast: true,
+            timeout_secs: None,
+            build_timeout_secs: None,
+            remote_url: None,
+            webhook_secret: None,
+            on_success_webhook: None,
+            on_failure_webhook: None,
+            order: 0,
+            schedule: None,
+            output_encoding: OutputEncoding::default(),
+            output_mode: OutputMode::default(),
+            template: None,
+            extra_commands: Vec::new(),
+            cleanup_commands: Vec::new(),
+            capture_versions: Vec::new(),
+            consecutive_failures_to_fail: default_consecutive_failures_to_fail(),
+            retries: 0,
+            retry_on_exit_codes: Vec::new(),
+            parallel: false,
+            max_parallel_commands: None,
+            trigger_paths: Vec::new(),
+            ignore_paths: Vec::new(),
+            author_filter: None,
+            dedup_by: DedupBy::default(),
+            active_hours: None,
+            update_submodules: None,
+            supersede_on_new_commit: false,
+            isolated: false,
+            escalation: Vec::new(),
+            run_as_user: None,
+            nice_level: None,
+            auto_redetect: false,
+        }.with_repo_ci_file())
     }
     
-    fn get_default_commands(project_type: &ProjectType) -> Vec<String> {
+    /// Checks everything a runner needs before it starts, returning all problems found
+    pub fn validate(&self, git: &str) -> Vec<String> {
+        let mut problems = Vec::new();
+        let root = std::path::Path::new(&self.path);
+        
+        if !root.is_dir() {
+            problems.push(format!("Path does not exist: {}", self.path));
+            return problems;
+        }
+        
+        let is_git_repo = std::process::Command::new(git)
+            .args(["rev-parse", "--git-dir"])
+            .current_dir(root)
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false);
+        if !is_git_repo {
+            problems.push(format!("Not a git repository: {}", self.path));
+        }
+        
+        for sub in &self.subprojects {
+            if !root.join(&sub.work_dir).is_dir() {
+                problems.push(format!("Subproject '{}' work_dir does not exist: {}", sub.name, sub.work_dir));
+            }
+        }
+        
+        if let Some(Err(e)) = self.parse_schedule() {
+            problems.push(format!("Invalid schedule: {}", e));
+        }
+        if let Some(Err(e)) = self.parse_active_hours() {
+            problems.push(format!("Invalid active_hours: {}", e));
+        }
+        #[cfg(unix)]
+        if let Some(user) = &self.run_as_user
+            && let Err(e) = crate::ci_runner::lookup_user(user)
+        {
+            problems.push(format!("Invalid run_as_user: {}", e));
+        }
+        if let Some(level) = self.nice_level
+            && !NICE_LEVELS.contains(&level)
+        {
+            problems.push(format!("Invalid nice_level {}: must be between -20 and 19", level));
+        }
+        if self.escalation.iter().any(|step| step.after_failures == 0) {
+            problems.push("Escalation steps need after_failures of at least 1".to_string());
+        }
+        for (field, url) in [("on_success_webhook", &self.on_success_webhook), ("on_failure_webhook", &self.on_failure_webhook)] {
+            if let Some(url) = url
+                && !reqwest::Url::parse(url).is_ok_and(|url| matches!(url.scheme(), "http" | "https"))
+            {
+                problems.push(format!("Invalid {}: {} is not an http(s) URL", field, url));
+            }
+        }
+        
+        problems
+    }
+    
+    /// Parses `schedule`, accepting standard 5-field cron as well as the
+    /// seconds-first 6/7-field form
+    pub fn parse_schedule(&self) -> Option<Result<cron::Schedule, String>> {
+        let expr = self.schedule.as_ref()?;
+        let expr = if expr.split_whitespace().count() == 5 {
+            format!("0 {}", expr)
+        } else {
+            expr.clone()
+        };
+        Some(expr.parse::<cron::Schedule>().map_err(|e| e.to_string()))
+    }
+    
+    pub fn parse_active_hours(&self) -> Option<Result<ActiveHours, String>> {
+        self.active_hours.as_deref().map(ActiveHours::parse)
+    }
+    
+    /// The repository as a runner would build it: template and repository CI
+    /// file applied, subproject defaults filled in and overrides resolved
+    pub fn effective(self, config: &Config) -> Self {
+        let mut repository = config.apply_template(self).with_repo_ci_file();
+        for sub in &mut repository.subprojects {
+            sub.commands = sub.effective_commands();
+        }
+        repository.max_parallel_commands = Some(repository.max_parallel_commands.unwrap_or(config.max_parallel_commands));
+        repository.run_as_user = config.run_as_user_for(&repository);
+        repository.nice_level = config.nice_level_for(&repository);
+        if let Ok(path) = std::fs::canonicalize(&repository.path) {
+            repository.path = path.to_string_lossy().to_string();
+        }
+        repository
+    }
+    
+    /// Masks `webhook_secret` and `env` values, as the API does
+    pub fn hide_secrets(&mut self) {
+        if self.webhook_secret.is_some() {
+            self.webhook_secret = Some(MASKED.to_string());
+        }
+        for value in self.env.values_mut() {
+            *value = MASKED.to_string();
+        }
+    }
+    
+    /// Applies `.turbulent-ci.toml` from the repository root, if present
+    pub fn with_repo_ci_file(mut self) -> Self {
+        if let Some(file) = RepoCiFile::load(&self.path) {
+            if let Some(commands) = file.commands {
+                self.commands = commands;
+            }
+            if let Some(env) = file.env {
+                self.env.extend(env);
+            }
+            if file.timeout.is_some() {
+                self.timeout_secs = file.timeout;
+            }
+        }
+        self
+    }
+    
+    /// Whether `commands` are still the defaults for `project_type`. Only
+    /// the command lines are compared, as repositories added before the
+    /// defaults had labels store them as plain strings.
+    pub fn has_default_commands(&self) -> bool {
+        let defaults = Self::get_default_commands(&self.project_type);
+        self.commands.len() == defaults.len()
+            && self.commands.iter().zip(&defaults).all(|(spec, default)| spec.cmd == default.cmd)
+    }
+    
+    /// Whether a build would run nothing at all, e.g. after clearing
+    /// `commands` or with a template that lists none
+    pub fn has_no_commands(&self) -> bool {
+        if self.subprojects.is_empty() {
+            self.commands.is_empty()
+        } else {
+            self.subprojects.iter().all(|sub| sub.effective_commands().is_empty())
+        }
+    }
+    
+    /// Switches to another project type, along with its default commands
+    /// unless the user has set their own
+    pub fn change_project_type(&mut self, project_type: ProjectType) {
+        if self.has_default_commands() {
+            self.commands = Self::get_default_commands(&project_type);
+        }
+        self.project_type = project_type;
+    }
+    
+    fn get_default_commands(project_type: &ProjectType) -> Vec<CommandSpec> {
         match project_type {
             ProjectType::Rust => vec![
-                "cargo check".to_string(),
-                "cargo test".to_string(),
-                "cargo clippy -- -D warnings".to_string(),
+                CommandSpec::named("Check", "cargo check"),
+                CommandSpec::named("Test", "cargo test"),
+                CommandSpec::named("Lint", "cargo clippy -- -D warnings").skip_if_missing(),
             ],
             ProjectType::Python => vec![
-                "python -m py_compile $(find . -name '*.py' | head -10)".to_string(),
-                "python -m pytest".to_string(),
-                "python -m flake8 --max-line-length=88".to_string(),
+                CommandSpec::named("Compile", "python -m py_compile $(find . -name '*.py' | head -10)"),
+                CommandSpec::named("Test", "python -m pytest"),
+                CommandSpec::named("Lint", "python -m flake8 --max-line-length=88").skip_if_missing(),
             ],
             ProjectType::Node => vec![
-                "npm ci".to_string(),
-                "npm test".to_string(),
-                "npm run lint".to_string(),
+                CommandSpec::named("Install", "npm ci"),
+                CommandSpec::named("Test", "npm test"),
+                CommandSpec::named("Lint", "npm run lint"),
+            ],
+            ProjectType::Deno => vec![
+                CommandSpec::named("Lint", "deno lint"),
+                CommandSpec::named("Test", "deno test"),
+                CommandSpec::named("Format", "deno fmt --check"),
+            ],
+            ProjectType::Bun => vec![
+                CommandSpec::named("Install", "bun install --frozen-lockfile"),
+                CommandSpec::named("Test", "bun test"),
+                CommandSpec::named("Lint", "bun run lint"),
             ],
             ProjectType::Generic => vec![
-                "echo 'Generic project - no default commands'".to_string(),
+                CommandSpec::new("echo 'Generic project - no default commands'"),
             ],
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A Rust repository as stored before commands had labels
+    fn stored_rust_repository() -> Repository {
+        serde_json::from_str(r#"{
+            "id": "5f0c6a1e-3b7d-4f2a-9c1e-8d4b2a6f7e10",
+            "name": "app",
+            "path": "/srv/app",
+            "project_type": "Rust",
+            "commands": ["cargo check", "cargo test", "cargo clippy -- -D warnings"],
+            "enabled": true
+        }"#).unwrap()
+    }
+
+    #[test]
+    fn unlabelled_defaults_count_as_default() {
+        assert!(stored_rust_repository().has_default_commands());
+    }
+
+    #[test]
+    fn changing_type_replaces_unlabelled_defaults() {
+        let mut repo = stored_rust_repository();
+        repo.change_project_type(ProjectType::Node);
+        let commands: Vec<&str> = repo.commands.iter().map(|spec| spec.cmd.as_str()).collect();
+        assert_eq!(commands, ["npm ci", "npm test", "npm run lint"]);
+    }
+
+    #[test]
+    fn custom_commands_are_kept() {
+        let mut repo = stored_rust_repository();
+        repo.commands.push(CommandSpec::new("cargo doc"));
+        assert!(!repo.has_default_commands());
+        repo.change_project_type(ProjectType::Node);
+        assert_eq!(repo.commands.len(), 4);
+    }
+
+    fn step(name: &str, depends_on: &[&str]) -> CommandSpec {
+        CommandSpec {
+            depends_on: depends_on.iter().map(|name| name.to_string()).collect(),
+            ..CommandSpec::named(name, "true")
+        }
+    }
+
+    #[test]
+    fn accepts_dependency_graph() {
+        let commands = [step("build", &[]), step("test", &["build"]), step("lint", &["build"]), step("package", &["test", "lint"])];
+        assert_eq!(validate_dependencies(&commands), Ok(()));
+    }
+
+    #[test]
+    fn rejects_dependency_cycle() {
+        let commands = [step("setup", &[]), step("a", &["setup", "c"]), step("b", &["a"]), step("c", &["b"])];
+        assert_eq!(validate_dependencies(&commands), Err("Dependency cycle between steps: a, b, c".to_string()));
+    }
+
+    #[test]
+    fn rejects_unknown_dependency() {
+        let commands = [step("build", &[]), step("test", &["biuld"])];
+        assert_eq!(validate_dependencies(&commands), Err("Step 'test' depends on unknown step 'biuld'".to_string()));
+    }
+
+    #[test]
+    fn rejects_duplicate_step_name() {
+        let commands = [step("test", &[]), step("test", &[])];
+        assert_eq!(
+            validate_dependencies(&commands),
+            Err("Step name 'test' is used more than once, so depends_on cannot refer to it".to_string())
+        );
+    }
+
+    fn at(time: &str) -> chrono::NaiveTime {
+        chrono::NaiveTime::parse_from_str(time, "%H:%M").unwrap()
+    }
+
+    #[test]
+    fn daytime_window_includes_start_but_not_end() {
+        let window = ActiveHours::parse("09:00-17:00").unwrap();
+        assert!(!window.contains(at("08:59")));
+        assert!(window.contains(at("09:00")));
+        assert!(window.contains(at("16:59")));
+        assert!(!window.contains(at("17:00")));
+        assert!(!window.contains(at("00:00")));
+    }
+
+    #[test]
+    fn window_can_span_midnight() {
+        let window = ActiveHours::parse("22:00 - 06:00").unwrap();
+        assert!(!window.contains(at("21:59")));
+        assert!(window.contains(at("22:00")));
+        assert!(window.contains(at("23:59")));
+        assert!(window.contains(at("00:00")));
+        assert!(window.contains(at("05:59")));
+        assert!(!window.contains(at("06:00")));
+        assert!(!window.contains(at("12:00")));
+    }
+
+    #[test]
+    fn rejects_empty_or_malformed_window() {
+        assert_eq!(
+            ActiveHours::parse("00:00-00:00"),
+            Err("'00:00-00:00' starts and ends at the same time, so it is never active".to_string())
+        );
+        assert!(ActiveHours::parse("09:00").is_err());
+        assert!(ActiveHours::parse("9am-5pm").is_err());
+        assert!(ActiveHours::parse("09:00-24:00").is_err());
+    }
+}
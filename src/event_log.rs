@@ -0,0 +1,117 @@
+use crate::logging;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// State-directory file the event log is appended to when `persist_events` is on
+pub const EVENT_LOG_FILE: &str = "events.jsonl";
+
+// Events kept in memory, and in the file after it is compacted at startup
+const MAX_EVENTS: usize = 1000;
+
+/// Something that happened to the daemon or a repository, for the audit trail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Event {
+    pub timestamp: u64,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub repository: Option<String>,
+    pub message: String,
+}
+
+impl Event {
+    pub fn new(kind: &str, repository: Option<&str>, message: &str) -> Self {
+        Self {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            kind: kind.to_string(),
+            repository: repository.map(str::to_string),
+            message: message.to_string(),
+        }
+    }
+}
+
+// The most recent events, optionally mirrored to a JSON-lines file so the
+// trail survives restarts
+#[derive(Debug, Clone, Default)]
+pub struct EventLog {
+    file: Option<PathBuf>,
+    events: VecDeque<Event>,
+}
+
+impl EventLog {
+    /// Reads the newest events from `file` and keeps appending to it
+    pub fn load(file: PathBuf) -> Self {
+        let mut events: VecDeque<Event> = fs::read_to_string(&file)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| serde_json::from_str(line).ok())
+            .collect();
+        let trimmed = events.len() > MAX_EVENTS;
+        while events.len() > MAX_EVENTS {
+            events.pop_front();
+        }
+
+        let log = Self { file: Some(file), events };
+        if trimmed {
+            log.compact();
+        }
+        log
+    }
+
+    // Rewrites the file with only the events still kept
+    fn compact(&self) {
+        let Some(ref file) = self.file else {
+            return;
+        };
+        let content: String = self.events
+            .iter()
+            .filter_map(|event| serde_json::to_string(event).ok())
+            .map(|line| line + "\n")
+            .collect();
+        if let Err(e) = fs::write(file, content) {
+            logging::warn(None, "event_log_save_failed", &format!("Failed to compact event log: {}", e));
+        }
+    }
+
+    pub fn record(&mut self, event: Event) {
+        if let Some(ref file) = self.file {
+            append(file, &event);
+        }
+        self.events.push_back(event);
+        if self.events.len() > MAX_EVENTS {
+            self.events.pop_front();
+        }
+    }
+
+    /// Newest first, optionally only those of one repository
+    pub fn recent(&self, repository: Option<&str>, limit: usize) -> Vec<Event> {
+        self.events
+            .iter()
+            .rev()
+            .filter(|event| repository.is_none_or(|name| event.repository.as_deref() == Some(name)))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+/// Appends one event to a log file, for commands that change things while
+/// the daemon is not the one holding the log
+pub fn append(file: &Path, event: &Event) {
+    let result = serde_json::to_string(event)
+        .map_err(|e| e.to_string())
+        .and_then(|line| {
+            OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(file)
+                .and_then(|mut f| writeln!(f, "{}", line))
+                .map_err(|e| e.to_string())
+        });
+    if let Err(e) = result {
+        logging::warn(None, "event_log_save_failed", &format!("Failed to save event: {}", e));
+    }
+}
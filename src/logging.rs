@@ -0,0 +1,76 @@
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, clap::ValueEnum)]
+pub enum LogFormat {
+    /// Emoji-decorated lines for humans
+    #[default]
+    Human,
+    /// One JSON object per line for log aggregation
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+}
+
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+
+pub fn init(format: LogFormat) {
+    FORMAT.set(format).ok();
+}
+
+pub fn info(repo: Option<&str>, event: &str, message: &str) {
+    log(Level::Info, repo, event, message);
+}
+
+pub fn warn(repo: Option<&str>, event: &str, message: &str) {
+    log(Level::Warn, repo, event, message);
+}
+
+pub fn error(repo: Option<&str>, event: &str, message: &str) {
+    log(Level::Error, repo, event, message);
+}
+
+pub fn log(level: Level, repo: Option<&str>, event: &str, message: &str) {
+    match FORMAT.get().copied().unwrap_or_default() {
+        LogFormat::Human => {
+            let prefix = match level {
+                Level::Info => "",
+                Level::Warn => "Warning: ",
+                Level::Error => "Error: ",
+            };
+            match repo {
+                Some(repo) => println!("[{}] {}{}", repo, prefix, message),
+                None => println!("{}{}", prefix, message),
+            }
+        }
+        LogFormat::Json => {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0);
+            let line = serde_json::json!({
+                "timestamp": timestamp,
+                "level": match level {
+                    Level::Info => "info",
+                    Level::Warn => "warn",
+                    Level::Error => "error",
+                },
+                "repo": repo,
+                "event": event,
+                "message": strip_decoration(message),
+            });
+            println!("{}", line);
+        }
+    }
+}
+
+// Human messages lead with an emoji; structured logs don't need it
+fn strip_decoration(message: &str) -> &str {
+    message
+        .trim_start_matches(|c: char| !c.is_alphanumeric() && c != '[' && c != '(' && c != '\'')
+}
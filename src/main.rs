@@ -5,28 +5,58 @@ mod web_server;
 mod project_detector;
 mod repository_manager;
 mod cli;
+mod build_queue;
+mod build_store;
+mod notifier;
+mod test_report;
+mod logging;
+mod event_log;
+mod webhook;
 
-use config::Config;
+use build_queue::{BuildQueue, BuildSlots};
+use build_store::BuildStore;
+use event_log::{Event, EventLog};
+use config::{Config, ProjectType, Repository, StorageKind};
 use models::GlobalState;
+use notifier::Notifier;
 use ci_runner::CiRunner;
 use web_server::WebServer;
 use repository_manager::RepositoryManager;
-use cli::{Cli, Commands};
+use project_detector::ProjectDetector;
+use cli::{Cli, Commands, ConfigAction, ConfigFormat};
 use clap::Parser;
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
 use std::process;
+use tokio::sync::Mutex;
+
+// How often `trigger --wait` checks on the build
+const WAIT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// How long shutdown waits for the stop notification before exiting anyway
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
 
 #[tokio::main]
 async fn main() {
     let cli = Cli::parse();
+    if let Some(dir) = cli.config_dir {
+        config::set_config_dir(dir);
+    }
+    if let Some(profile) = cli.profile {
+        config::set_profile(profile);
+    }
     
     match cli.command {
-        Commands::Start { port, config_file } => {
-            start_daemon(port, config_file).await;
+        Commands::Start { port, config_file, log_format, strict, auto_port, force, rebuild_on_start } => {
+            logging::init(log_format);
+            start_daemon(port, config_file, strict, auto_port, force, rebuild_on_start).await;
         }
-        Commands::Add { path, name } => {
-            add_repository(path, name).await;
+        Commands::Add { path, name, project_type, clone_to } => {
+            add_repository(path, name, project_type, clone_to).await;
+        }
+        Commands::Detect { path } => {
+            detect_project(&path);
         }
         Commands::Remove { name } => {
             remove_repository(name).await;
@@ -34,54 +64,300 @@ async fn main() {
         Commands::List => {
             list_repositories().await;
         }
+        Commands::Export { file } => {
+            export_repositories(file).await;
+        }
+        Commands::Import { file, base_path } => {
+            import_repositories(file, base_path).await;
+        }
+        Commands::Reorder { name, position } => {
+            reorder_repository(name, position).await;
+        }
+        Commands::Show { name, json } => {
+            show_repository(name, json).await;
+        }
         Commands::Status => {
             show_status().await;
         }
+        Commands::Trigger { name, all, wait, timeout } => {
+            let previous = match (&name, wait) {
+                (Some(name), true) => Some(latest_build_id(name).await),
+                _ => None,
+            };
+            trigger_builds(name.clone(), all).await;
+            if let (Some(name), Some(previous)) = (name, previous) {
+                wait_for_builds(&name, previous, timeout.map(Duration::from_secs)).await;
+            }
+        }
+        Commands::Ack { name } => {
+            acknowledge(&name).await;
+        }
+        Commands::Pause => {
+            set_paused(true).await;
+        }
+        Commands::Resume => {
+            set_paused(false).await;
+        }
+        Commands::Rebuild { build_id } => {
+            rerun_build(build_id).await;
+        }
+        Commands::Config { action: ConfigAction::Show { format } } => {
+            show_config(format).await;
+        }
     }
 }
 
-async fn start_daemon(port: Option<u16>, config_file: Option<String>) {
-    let config = Config::new(port.unwrap_or(3030), config_file);
+async fn start_daemon(port: Option<u16>, config_file: Option<String>, strict: bool, auto_port: bool, force: bool, rebuild_on_start: bool) {
+    let mut config = Config::new(port.unwrap_or(3030), config_file);
+    config.auto_port = auto_port;
+    config.rebuild_on_start = rebuild_on_start;
+    require_writable(&config);
     let repo_manager = RepositoryManager::load(&config).unwrap_or_else(|e| {
-        println!("Warning: Failed to load repositories: {}", e);
+        logging::warn(None, "repositories_load_failed", &format!("Failed to load repositories: {}", e));
         RepositoryManager::new()
     });
     
-    println!("🌪️  Turbulent CI Multi-Repository Daemon");
-    println!("📁 Config file: {}", config.config_file);
-    println!("🌐 Web interface: http://localhost:{}", config.web_port);
+    match config.check_git() {
+        Ok(version) => logging::info(None, "git_detected", &format!("🔧 Using {}", version)),
+        Err(e) => {
+            logging::error(None, "git_unavailable", &e);
+            process::exit(1);
+        }
+    }
+    
+    for problem in config.validate_notifier_refs(&repo_manager.get_repositories()) {
+        logging::warn(None, "invalid_notifier_ref", &problem);
+    }
+    for problem in config.validate_template_refs(&repo_manager.get_repositories()) {
+        logging::warn(None, "invalid_template_ref", &problem);
+    }
+    for problem in config.validate_project_refs(&repo_manager.get_repositories()) {
+        logging::warn(None, "invalid_project_ref", &problem);
+    }
     
-    let global_state = Arc::new(Mutex::new(GlobalState::new()));
+    logging::info(None, "daemon_started", "🌪️  Turbulent CI Multi-Repository Daemon");
+    logging::info(None, "daemon_started", &format!("📁 Config file: {}", config.config_file));
+    if let Some(profile) = config::profile() {
+        logging::info(None, "daemon_started", &format!("👤 Profile: {}", profile));
+    }
+    
+    let mut initial_state = GlobalState::new();
+    initial_state.set_max_build_age(config.max_build_age());
+    initial_state.build_queue = BuildQueue::load(config.state_file("pending_builds.json"));
+    initial_state.build_slots = Arc::new(BuildSlots::new(config.max_concurrent_builds, &config.max_concurrent_builds_by_type));
+    if config.persist_events {
+        initial_state.events = EventLog::load(config.state_file(event_log::EVENT_LOG_FILE));
+    }
+    if config.storage == StorageKind::Sqlite {
+        let path = config.database_file();
+        match BuildStore::open(&path) {
+            Ok(store) => {
+                logging::info(None, "build_store_opened", &format!("🗄️  Build database: {}", path.display()));
+                initial_state.attach_store(store);
+            }
+            Err(e) => logging::warn(None, "build_store_failed", &format!(
+                "Cannot open build database {}: {}; keeping builds in memory", path.display(), e
+            )),
+        }
+    }
+    let interrupted = build_queue::take_interrupted(&config.state_file(build_queue::CHECKPOINT_DIR));
+    if !interrupted.is_empty() {
+        logging::warn(None, "builds_interrupted", &format!(
+            "⚠️  Recording {} build(s) interrupted by the last shutdown", interrupted.len()
+        ));
+        initial_state.add_interrupted_builds(interrupted);
+    }
+    let global_state = Arc::new(Mutex::new(initial_state));
     let global_state_clone = Arc::clone(&global_state);
     
+    let repositories = repo_manager.get_repositories()
+        .into_iter()
+        .map(|mut repo| {
+            // Resolved once, so validation checks the values the runner uses
+            repo.run_as_user = config.run_as_user_for(&repo);
+            repo.nice_level = config.nice_level_for(&repo);
+            #[cfg(not(unix))]
+            if let Some(user) = repo.run_as_user.take() {
+                logging::warn(Some(&repo.name), "run_as_user_unsupported", &format!(
+                    "run_as_user is only supported on Unix; commands run as the daemon's user instead of '{}'", user
+                ));
+            }
+            #[cfg(not(unix))]
+            if repo.nice_level.take().is_some() {
+                logging::warn(Some(&repo.name), "nice_level_unsupported", "nice_level is only supported on Unix; commands run at normal priority");
+            }
+            config.apply_template(repo)
+        })
+        .collect();
+    let repositories: Vec<Repository> = validate_repositories(repositories, config.git(), strict);
+    let repository_count = repositories.len();
+    check_repository_limit(repository_count, config.max_repositories, force);
+    
     // Start CI runners for each repository
-    let repositories = repo_manager.get_repositories().clone();
-    for repo in repositories {
+    for (index, repo) in repositories.into_iter().enumerate() {
         let repo_clone = repo.clone();
+        let config_clone = config.clone();
         let state_clone = Arc::clone(&global_state);
+        // Spread out the first poll so repositories don't all build at once
+        let delay = Duration::from_secs(config.startup_stagger_secs * index as u64);
         
-        thread::spawn(move || {
-            let mut runner = CiRunner::new(repo_clone, state_clone);
-            runner.run();
+        tokio::spawn(async move {
+            let runner = CiRunner::new(repo_clone, &config_clone, state_clone).await;
+            tokio::time::sleep(delay).await;
+            runner.run().await;
         });
     }
     
     // Start web server
-    let web_server = WebServer::new(global_state_clone, config.web_port);
-    web_server.start().await;
+    let web_server = WebServer::new(global_state_clone, config.clone());
+    let (addr, server) = match web_server.bind() {
+        Ok(bound) => bound,
+        Err(e) => {
+            logging::error(None, "web_failed", &e);
+            process::exit(1);
+        }
+    };
+    let url_file = config::daemon_url_file();
+    if let Err(e) = std::fs::write(&url_file, format!("http://localhost:{}", addr.port())) {
+        logging::warn(None, "daemon_url_save_failed", &format!("Failed to write {}: {}; CLI commands won't find this daemon", url_file.display(), e));
+    }
+    
+    let host = notifier::hostname();
+    let version = env!("CARGO_PKG_VERSION");
+    global_state.lock().await.record_event("daemon_started", None, &format!(
+        "Turbulent CI {} started on {}, monitoring {} repositories", version, host, repository_count
+    ));
+    notify_lifecycle(&config, "daemon_started", format!(
+        "🌪️ Turbulent CI {} started on {}, monitoring {} repositories", version, host, repository_count
+    ));
+    
+    tokio::select! {
+        _ = server => {}
+        _ = shutdown_signal() => {}
+    }
+    
+    logging::info(None, "daemon_stopping", "🛑 Shutting down");
+    global_state.lock().await.record_event("daemon_stopped", None, &format!("Turbulent CI {} stopping on {}", version, host));
+    let stopping = notify_lifecycle(&config, "daemon_stopping", format!("🛑 Turbulent CI {} stopping on {}", version, host));
+    if tokio::time::timeout(SHUTDOWN_GRACE, stopping).await.is_err() {
+        logging::warn(None, "notification_failed", "Stop notification did not finish within the grace period");
+    }
+    std::fs::remove_file(&url_file).ok();
+    process::exit(0);
 }
 
-async fn add_repository(path: String, name: Option<String>) {
+// Sends a daemon lifecycle message to every notifier when `notify_lifecycle` is on
+fn notify_lifecycle(config: &Config, event: &'static str, message: String) -> tokio::task::JoinHandle<()> {
+    let notifiers = if config.notify_lifecycle { config.notifiers.clone() } else { Vec::new() };
+    
+    tokio::spawn(async move {
+        for notifier in notifiers.into_iter().map(Notifier::new) {
+            if let Err(e) = notifier.send_lifecycle(event, &message).await {
+                logging::warn(None, "notification_failed", &format!("Notifier '{}' failed: {}", notifier.name(), e));
+            }
+        }
+    })
+}
+
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(signal) => signal,
+            Err(_) => {
+                tokio::signal::ctrl_c().await.ok();
+                return;
+            }
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await.ok();
+    }
+}
+
+// Every repository polls git on its own schedule, so very large
+// configurations are refused unless forced
+fn check_repository_limit(count: usize, limit: usize, force: bool) {
+    if count > limit {
+        let message = format!("{} repositories exceed max_repositories ({})", count, limit);
+        if !force {
+            logging::error(None, "too_many_repositories", &format!(
+                "{}; raise max_repositories in settings.json or pass --force", message
+            ));
+            process::exit(1);
+        }
+        logging::warn(None, "too_many_repositories", &format!("{}; starting anyway (--force)", message));
+    }
+    logging::info(None, "runners_starting", &format!(
+        "🧵 Starting {} repository runners", count
+    ));
+}
+
+// Reports every misconfigured repository at once; with `strict` any problem is
+// fatal, otherwise the affected repositories are skipped
+fn validate_repositories(repositories: Vec<Repository>, git: &str, strict: bool) -> Vec<Repository> {
+    let mut valid = Vec::new();
+    let mut invalid = 0;
+    
+    for repo in repositories {
+        let problems = repo.validate(git);
+        if problems.is_empty() {
+            valid.push(repo);
+            continue;
+        }
+        
+        invalid += 1;
+        for problem in problems {
+            logging::error(Some(&repo.name), "invalid_repository", &problem);
+        }
+    }
+    
+    if invalid > 0 {
+        if strict {
+            logging::error(None, "validation_failed", &format!("{} repositories failed validation; refusing to start (--strict)", invalid));
+            process::exit(1);
+        }
+        logging::warn(None, "validation_failed", &format!("Skipping {} repositories that failed validation", invalid));
+    }
+    
+    valid
+}
+
+// Commands that save state fail early with an actionable message instead of after the work is done
+fn require_writable(config: &Config) {
+    if let Err(e) = config.ensure_writable() {
+        eprintln!("❌ {}", e);
+        process::exit(1);
+    }
+}
+
+async fn add_repository(path: String, name: Option<String>, project_type: Option<ProjectType>, clone_to: Option<String>) {
     let config = Config::default();
+    require_writable(&config);
     let mut repo_manager = RepositoryManager::load(&config).unwrap_or_else(|_| RepositoryManager::new());
     
-    match repo_manager.add_repository(path, name) {
+    let result = match clone_to {
+        Some(clone_to) => {
+            println!("📥 Cloning {} into {}...", path, clone_to);
+            repo_manager.add_remote_repository(config.git(), path, clone_to, name, project_type)
+        }
+        None => repo_manager.add_repository(path, name, project_type),
+    };
+    
+    match result {
         Ok(repo) => {
             if let Err(e) = repo_manager.save(&config) {
                 eprintln!("Failed to save configuration: {}", e);
                 process::exit(1);
             }
             println!("✅ Added repository: {} ({})", repo.name, repo.path);
+            record_event(&config, "repository_added", &repo.name, &format!("Added {}", repo.path));
+            warn_if_no_commands(&config, &repo);
             println!("💡 Restart the daemon to begin monitoring this repository");
         }
         Err(e) => {
@@ -91,8 +367,42 @@ async fn add_repository(path: String, name: Option<String>) {
     }
 }
 
+// Repository changes made from the command line join the daemon's persisted
+// event log; without one there's no trail to add them to
+fn record_event(config: &Config, kind: &str, repository: &str, message: &str) {
+    if config.persist_events {
+        event_log::append(&config.state_file(event_log::EVENT_LOG_FILE), &Event::new(kind, Some(repository), message));
+    }
+}
+
+// Such repositories never build, so saying so now beats a puzzling dashboard later
+fn warn_if_no_commands(config: &Config, repo: &Repository) {
+    if repo.clone().effective(config).has_no_commands() {
+        println!("⚠️  {} has no commands to run; it will show NoCommands instead of building", repo.name);
+    }
+}
+
+fn detect_project(path: &str) {
+    if !std::path::Path::new(path).is_dir() {
+        eprintln!("❌ Not a directory: {}", path);
+        process::exit(1);
+    }
+    
+    let detection = ProjectDetector::new().detect_project_type(path);
+    println!("🔍 {}", path);
+    if detection.indicators.is_empty() {
+        println!("  (no indicators)");
+    }
+    for indicator in &detection.indicators {
+        println!("  {} → {:?}", indicator.file, indicator.project_type);
+    }
+    println!("✅ Detected {:?}: {}", detection.project_type, detection.reason);
+    println!("💡 Use `add --type <type>` to pick a different type");
+}
+
 async fn remove_repository(name: String) {
     let config = Config::default();
+    require_writable(&config);
     let mut repo_manager = RepositoryManager::load(&config).unwrap_or_else(|_| RepositoryManager::new());
     
     if repo_manager.remove_repository(&name) {
@@ -101,6 +411,7 @@ async fn remove_repository(name: String) {
             process::exit(1);
         }
         println!("✅ Removed repository: {}", name);
+        record_event(&config, "repository_removed", &name, "Removed");
         println!("💡 Restart the daemon to stop monitoring this repository");
     } else {
         eprintln!("❌ Repository '{}' not found", name);
@@ -124,11 +435,229 @@ async fn list_repositories() {
     }
 }
 
+async fn export_repositories(file: String) {
+    let config = Config::default();
+    let repo_manager = RepositoryManager::load(&config).unwrap_or_else(|_| RepositoryManager::new());
+    
+    match repo_manager.export(&file) {
+        Ok(count) => println!("✅ Exported {} repositories to {}", count, file),
+        Err(e) => {
+            eprintln!("❌ Failed to export repositories: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+async fn import_repositories(file: String, base_path: Option<String>) {
+    let config = Config::default();
+    require_writable(&config);
+    let mut repo_manager = RepositoryManager::load(&config).unwrap_or_else(|_| RepositoryManager::new());
+    
+    let (imported, skipped) = match repo_manager.import(&file, base_path.as_deref()) {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("❌ Failed to import repositories: {}", e);
+            process::exit(1);
+        }
+    };
+    
+    if let Err(e) = repo_manager.save(&config) {
+        eprintln!("Failed to save configuration: {}", e);
+        process::exit(1);
+    }
+    
+    for repo in &imported {
+        let missing = if std::path::Path::new(&repo.path).exists() { "" } else { " ⚠️  path does not exist" };
+        println!("  + {} ({}){}", repo.name, repo.path, missing);
+        warn_if_no_commands(&config, repo);
+        record_event(&config, "repository_added", &repo.name, &format!("Imported {} from {}", repo.path, file));
+    }
+    for repo in &skipped {
+        println!("  = {} ({}) already configured, skipped", repo.name, repo.path);
+    }
+    println!("✅ Imported {} repositories, skipped {}", imported.len(), skipped.len());
+    println!("💡 Restart the daemon to begin monitoring imported repositories");
+}
+
+async fn reorder_repository(name: String, position: usize) {
+    let config = Config::default();
+    require_writable(&config);
+    let mut repo_manager = RepositoryManager::load(&config).unwrap_or_else(|_| RepositoryManager::new());
+    
+    if let Err(e) = repo_manager.reorder_repository(&name, position) {
+        eprintln!("❌ {}", e);
+        process::exit(1);
+    }
+    if let Err(e) = repo_manager.save(&config) {
+        eprintln!("Failed to save configuration: {}", e);
+        process::exit(1);
+    }
+    
+    println!("✅ Moved {} to position {}", name, position.max(1));
+    println!("💡 Restart the daemon to apply the new order");
+}
+
+// Where configuration comes from, the settings and the repositories as resolved
+#[derive(serde::Serialize)]
+struct EffectiveConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    profile: Option<&'static str>,
+    config_dir: PathBuf,
+    repositories_file: String,
+    settings_file: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    database_file: Option<PathBuf>,
+    settings: Config,
+    repositories: Vec<Repository>,
+}
+
+async fn show_config(format: ConfigFormat) {
+    let mut config = Config::default();
+    let repo_manager = RepositoryManager::load(&config).unwrap_or_else(|e| {
+        eprintln!("❌ Failed to load repositories: {}", e);
+        process::exit(1);
+    });
+    
+    // Secrets are masked, so the output can go into a support ticket
+    let repositories: Vec<Repository> = repo_manager.get_repositories()
+        .into_iter()
+        .map(|repo| {
+            let mut repo = repo.effective(&config);
+            repo.hide_secrets();
+            repo
+        })
+        .collect();
+    config.hide_secrets();
+    
+    let effective = EffectiveConfig {
+        profile: config::profile(),
+        config_dir: config::config_dir(),
+        repositories_file: config.config_file.clone(),
+        settings_file: config::settings_file(),
+        database_file: (config.storage == StorageKind::Sqlite).then(|| config.database_file()),
+        settings: config,
+        repositories,
+    };
+    
+    let output = match format {
+        ConfigFormat::Json => serde_json::to_string_pretty(&effective).map_err(|e| e.to_string()),
+        ConfigFormat::Toml => toml::to_string_pretty(&effective).map_err(|e| e.to_string()),
+    };
+    match output {
+        Ok(output) => println!("{}", output.trim_end()),
+        Err(e) => {
+            eprintln!("❌ Failed to render configuration: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+async fn show_repository(name: String, json: bool) {
+    let config = Config::default();
+    let repo_manager = RepositoryManager::load(&config).unwrap_or_else(|_| RepositoryManager::new());
+    
+    let Some(mut repo) = repo_manager.find_repository(&name).cloned().map(|repo| repo.effective(&config)) else {
+        eprintln!("❌ Repository '{}' not found", name);
+        process::exit(1);
+    };
+    repo.hide_secrets();
+    
+    // Live state is optional; the daemon may not be running
+    let live = match daemon_url() {
+        Some(daemon) => match reqwest::get(format!("{}/api/repository/{}", daemon, name)).await {
+            Ok(response) => response.json::<serde_json::Value>().await.ok().filter(|v| v.get("error").is_none()),
+            Err(_) => None,
+        },
+        None => None,
+    };
+    
+    if json {
+        let output = serde_json::json!({ "repository": repo, "live": live });
+        println!("{}", serde_json::to_string_pretty(&output).unwrap_or_default());
+        return;
+    }
+    
+    println!("📦 {}", repo.name);
+    println!("  {:<12} {}", "ID", repo.id);
+    println!("  {:<12} {}", "Path", repo.path);
+    if let Some(ref remote) = repo.remote_url {
+        println!("  {:<12} {}", "Remote", remote);
+    }
+    println!("  {:<12} {:?}", "Type", repo.project_type);
+    println!("  {:<12} {}", "Enabled", repo.enabled);
+    println!("  {:<12} {}", "Fail fast", repo.fail_fast);
+    println!("  {:<12} {}", "Strip ANSI", repo.strip_ansi);
+    if let Some(ref schedule) = repo.schedule {
+        println!("  {:<12} {}", "Schedule", schedule);
+    }
+    if let Some(timeout) = repo.timeout_secs {
+        println!("  {:<12} {}s", "Timeout", timeout);
+    }
+    if !repo.notifiers.is_empty() {
+        println!("  {:<12} {}", "Notifiers", repo.notifiers.join(", "));
+    }
+    if !repo.env.is_empty() {
+        let keys: Vec<&str> = repo.env.keys().map(|k| k.as_str()).collect();
+        println!("  {:<12} {}", "Env", keys.join(", "));
+    }
+    if let Some(ref template) = repo.template {
+        println!("  {:<12} {}", "Template", template);
+    }
+    println!("  Commands:");
+    for cmd in &repo.commands {
+        if cmd.name == cmd.cmd {
+            println!("    • {}", cmd.cmd);
+        } else {
+            println!("    • {}: {}", cmd.name, cmd.cmd);
+        }
+    }
+    for sub in &repo.subprojects {
+        println!("  Subproject {} ({:?}) in {}", sub.name, sub.project_type, sub.work_dir);
+    }
+    
+    match live {
+        Some(live) => {
+            println!();
+            println!("  {:<12} {}", "Status", live["current_status"].as_str().unwrap_or("unknown"));
+            println!("  {:<12} {}", "Branch", live["repo_info"]["branch"].as_str().unwrap_or("unknown"));
+            println!("  {:<12} {}", "Last commit", live["repo_info"]["last_commit"].as_str().unwrap_or("unknown"));
+            println!("  Recent builds:");
+            let builds = live["builds"].as_array().cloned().unwrap_or_default();
+            if builds.is_empty() {
+                println!("    (none)");
+            }
+            for build in builds.iter().take(5) {
+                println!("    {} #{:<5} {:<10} {}ms",
+                         if build["success"].as_bool().unwrap_or(false) { "✅" } else { "❌" },
+                         build["id"].as_u64().unwrap_or(0),
+                         build["commit_hash"].as_str().unwrap_or("").chars().take(8).collect::<String>(),
+                         build["duration_ms"].as_u64().unwrap_or(0));
+            }
+        }
+        None => println!("\n💡 Daemon not running; live status unavailable"),
+    }
+}
+
 async fn show_status() {
-    match reqwest::get("http://localhost:3030/api/status").await {
+    let Some(daemon) = daemon_url() else {
+        println!("❌ Turbulent CI daemon is not running or not accessible");
+        return;
+    };
+    match reqwest::get(format!("{}/api/status", daemon)).await {
         Ok(response) => {
             if response.status().is_success() {
                 println!("✅ Turbulent CI daemon is running");
+                if let Ok(status) = response.json::<serde_json::Value>().await {
+                    if let Some(version) = status["version"].as_str() {
+                        println!("📦 Version: {}", version);
+                    }
+                    if let Some(uptime) = status["uptime_secs"].as_u64() {
+                        println!("⏱️  Uptime: {}", format_duration(uptime));
+                    }
+                    if status["paused"].as_bool() == Some(true) {
+                        println!("⏸️  Builds are paused (turbulent-ci resume)");
+                    }
+                }
             } else {
                 println!("❌ Daemon responded with error: {}", response.status());
             }
@@ -138,3 +667,199 @@ async fn show_status() {
         }
     }
 }
+
+async fn trigger_builds(name: Option<String>, all: bool) {
+    let url = match name {
+        Some(ref name) if !all => format!("{}/api/repository/{}/trigger", require_daemon_url(), name),
+        _ => format!("{}/api/trigger-all", require_daemon_url()),
+    };
+    
+    let response = match reqwest::Client::new().post(&url).send().await {
+        Ok(response) => response,
+        Err(_) => {
+            eprintln!("❌ Turbulent CI daemon is not running or not accessible");
+            process::exit(1);
+        }
+    };
+    
+    match response.json::<serde_json::Value>().await {
+        Ok(body) => {
+            if let Some(error) = body["error"].as_str() {
+                eprintln!("❌ {}", error);
+                process::exit(1);
+            }
+            let queued = body["queued"].as_u64().unwrap_or(0);
+            println!("🚀 Queued {} build(s)", queued);
+        }
+        Err(e) => {
+            eprintln!("❌ Invalid response from daemon: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+async fn acknowledge(name: &str) {
+    let url = format!("{}/api/repository/{}/acknowledge", require_daemon_url(), name);
+    let response = match reqwest::Client::new().post(&url).send().await {
+        Ok(response) => response,
+        Err(_) => {
+            eprintln!("❌ Turbulent CI daemon is not running or not accessible");
+            process::exit(1);
+        }
+    };
+    
+    match response.json::<serde_json::Value>().await {
+        Ok(body) => {
+            if let Some(error) = body["error"].as_str() {
+                eprintln!("❌ {}", error);
+                process::exit(1);
+            }
+            println!("👍 Acknowledged failure of {} until its next successful build", name);
+        }
+        Err(e) => {
+            eprintln!("❌ Invalid response from daemon: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+async fn set_paused(paused: bool) {
+    let url = format!("{}/api/{}", require_daemon_url(), if paused { "pause" } else { "resume" });
+    let response = match reqwest::Client::new().post(&url).send().await {
+        Ok(response) => response,
+        Err(_) => {
+            eprintln!("❌ Turbulent CI daemon is not running or not accessible");
+            process::exit(1);
+        }
+    };
+    
+    match response.json::<serde_json::Value>().await {
+        Ok(body) => {
+            if let Some(error) = body["error"].as_str() {
+                eprintln!("❌ {}", error);
+                process::exit(1);
+            }
+            if paused {
+                println!("⏸️  Builds paused; new commits are tracked and built after `turbulent-ci resume`");
+            } else {
+                println!("▶️  Builds resumed");
+            }
+        }
+        Err(e) => {
+            eprintln!("❌ Invalid response from daemon: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+// Address recorded by this profile's daemon, if one has been started
+fn daemon_url() -> Option<String> {
+    std::fs::read_to_string(config::daemon_url_file())
+        .ok()
+        .map(|url| url.trim().to_string())
+}
+
+fn require_daemon_url() -> String {
+    daemon_url().unwrap_or_else(|| {
+        eprintln!("❌ Turbulent CI daemon is not running or not accessible");
+        process::exit(1);
+    })
+}
+
+// Newest build id of a repository on the running daemon, 0 before its first build
+async fn latest_build_id(name: &str) -> u64 {
+    fetch_repository(name).await["builds"][0]["id"].as_u64().unwrap_or(0)
+}
+
+async fn fetch_repository(name: &str) -> serde_json::Value {
+    let url = format!("{}/api/repository/{}", require_daemon_url(), name);
+    let body = match reqwest::get(&url).await {
+        Ok(response) => response.json::<serde_json::Value>().await.ok(),
+        Err(_) => {
+            eprintln!("❌ Turbulent CI daemon is not running or not accessible");
+            process::exit(1);
+        }
+    };
+    match body {
+        Some(body) if body["error"].is_null() => body,
+        Some(body) => {
+            eprintln!("❌ {}", body["error"].as_str().unwrap_or("Repository not found"));
+            process::exit(1);
+        }
+        None => {
+            eprintln!("❌ Invalid response from daemon");
+            process::exit(1);
+        }
+    }
+}
+
+// Polls until the builds started after `previous` have finished, then exits
+// 0 if they all passed, 1 if any failed, or 2 on timeout
+async fn wait_for_builds(name: &str, previous: u64, timeout: Option<Duration>) -> ! {
+    let started = std::time::Instant::now();
+    println!("⏳ Waiting for {} to finish building...", name);
+    loop {
+        tokio::time::sleep(WAIT_POLL_INTERVAL).await;
+        let repo = fetch_repository(name).await;
+        let builds: Vec<&serde_json::Value> = repo["builds"]
+            .as_array()
+            .map(|builds| builds.iter().take_while(|build| build["id"].as_u64().unwrap_or(0) > previous).collect())
+            .unwrap_or_default();
+        let idle = !repo["is_building"].as_bool().unwrap_or(false) && !repo["build_requested"].as_bool().unwrap_or(false);
+        
+        if idle && !builds.is_empty() {
+            let passed = |build: &serde_json::Value| build["success"].as_bool().unwrap_or(false);
+            for build in builds.iter().rev() {
+                let icon = if passed(build) { "✅" } else { "❌" };
+                println!("{} Build #{} finished in {}ms", icon, build["id"], build["duration_ms"]);
+            }
+            process::exit(if builds.iter().all(|build| passed(build)) { 0 } else { 1 });
+        }
+        if timeout.is_some_and(|timeout| started.elapsed() >= timeout) {
+            eprintln!("⏱️  Timed out waiting for {} to finish", name);
+            process::exit(2);
+        }
+    }
+}
+
+async fn rerun_build(build_id: u64) {
+    let url = format!("{}/api/build/{}/rerun", require_daemon_url(), build_id);
+    let response = match reqwest::Client::new().post(&url).send().await {
+        Ok(response) => response,
+        Err(_) => {
+            eprintln!("❌ Turbulent CI daemon is not running or not accessible");
+            process::exit(1);
+        }
+    };
+    
+    match response.json::<serde_json::Value>().await {
+        Ok(body) => {
+            if let Some(error) = body["error"].as_str() {
+                eprintln!("❌ {}", error);
+                process::exit(1);
+            }
+            println!("🔁 Queued re-run of build #{}", build_id);
+        }
+        Err(e) => {
+            eprintln!("❌ Invalid response from daemon: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn format_duration(secs: u64) -> String {
+    let days = secs / 86400;
+    let hours = (secs % 86400) / 3600;
+    let minutes = (secs % 3600) / 60;
+    let seconds = secs % 60;
+    
+    if days > 0 {
+        format!("{}d {}h {}m", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}h {}m {}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
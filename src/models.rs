@@ -1,6 +1,12 @@
-use crate::config::{Repository};
+use crate::build_queue::{BuildQueue, BuildSlots};
+use crate::build_store::BuildStore;
+use crate::event_log::{Event, EventLog};
+use crate::logging;
+use crate::config::{CommandSpec, Project, Repository};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,12 +21,139 @@ pub struct BuildResult {
     pub duration_ms: u64,
     pub repo_path: String,
     pub project_type: String,
+    #[serde(default)]
+    pub subproject: Option<String>,
+    #[serde(default)]
+    pub steps: Vec<CommandResult>,
+    #[serde(default)]
+    pub trigger: BuildTrigger,
+    /// Id of the build this one reproduces
+    #[serde(default)]
+    pub rerun_of: Option<u64>,
+    /// Sequence number of this build within its repository, starting at 1
+    #[serde(default)]
+    pub repo_build_number: u64,
+    /// Free-form annotation added from the dashboard or API
+    #[serde(default)]
+    pub note: Option<String>,
+    /// Largest resident set of any command; None where the platform can't report it
+    #[serde(default)]
+    pub peak_memory_kb: Option<u64>,
+    /// User plus system CPU time summed over all commands
+    #[serde(default)]
+    pub cpu_time_ms: Option<u64>,
+    /// Average duration of recent successful builds of the same target
+    #[serde(default)]
+    pub baseline_duration_ms: Option<u64>,
+    /// Took longer than the baseline by more than `slow_build_factor`
+    #[serde(default)]
+    pub slow: bool,
+    /// Cancelled because a newer commit arrived while it ran
+    #[serde(default)]
+    pub superseded: bool,
+    /// The daemon stopped before the build finished; output is what ran so far
+    #[serde(default)]
+    pub interrupted: bool,
+    /// Test counts parsed from the output, for toolchains with a parser
+    #[serde(default)]
+    pub test_summary: Option<TestSummary>,
+    /// Output of each of the repository's `capture_versions` commands
+    #[serde(default)]
+    pub environment: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TestSummary {
+    pub passed: u32,
+    pub failed: u32,
+    /// Ignored or skipped tests
+    pub skipped: u32,
+}
+
+/// Why a build was started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum BuildTrigger {
+    #[default]
+    Commit,
+    Manual,
+    Webhook,
+    Scheduled,
+    #[allow(dead_code)]
+    Dependency,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandResult {
+    #[serde(default)]
+    pub name: String,
+    pub command: String,
+    pub success: bool,
+    pub duration_ms: u64,
+    /// Exit code of the last attempt
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub attempts: u32,
+    /// Optional command whose program wasn't installed
+    #[serde(default)]
+    pub skipped: bool,
+    #[serde(default)]
+    pub peak_memory_kb: Option<u64>,
+    /// User plus system CPU time across all attempts
+    #[serde(default)]
+    pub cpu_time_ms: Option<u64>,
+    /// This command's section of the build output, redacted
+    #[serde(default)]
+    pub output: String,
+    /// Output of the last attempt, with `output_mode: separate`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stdout: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stderr: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildComparison {
+    pub a: BuildResult,
+    pub b: BuildResult,
+    pub success_changed: bool,
+    pub duration_delta_ms: i64,
+    pub commands: Vec<CommandComparison>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandComparison {
+    pub name: String,
+    pub command: String,
+    pub a_success: Option<bool>,
+    pub b_success: Option<bool>,
+    pub success_changed: bool,
+    pub duration_delta_ms: Option<i64>,
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct GlobalState {
     pub repositories: HashMap<Uuid, RepositoryState>,
     pub recent_builds: Vec<BuildResult>,
+    pub started_at: u64,
+    #[serde(skip)]
+    pub build_queue: BuildQueue,
+    /// Concurrent build limits shared by all runners
+    #[serde(skip)]
+    pub build_slots: Arc<BuildSlots>,
+    #[serde(skip)]
+    last_build_id: u64,
+    /// Durable build history when SQLite storage is configured
+    #[serde(skip)]
+    store: Option<Arc<BuildStore>>,
+    /// With age-based retention, builds older than this are pruned instead
+    /// of keeping a fixed number
+    #[serde(skip)]
+    max_build_age: Option<Duration>,
+    /// Builds are deferred daemon-wide until resumed
+    pub paused: bool,
+    #[serde(skip)]
+    pub events: EventLog,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -29,6 +162,35 @@ pub struct RepositoryState {
     pub builds: Vec<BuildResult>,
     pub current_status: String,
     pub repo_info: RepoInfo,
+    pub build_requested: bool,
+    /// A webhook reported a push; the next poll looks for new commits early
+    #[serde(skip)]
+    pub webhook_received: bool,
+    /// Number of builds started for this repository
+    pub build_count: u64,
+    pub is_building: bool,
+    pub active_build_id: Option<u64>,
+    /// Estimated fraction of the running build done, from 0.0 to 1.0
+    pub progress: Option<f32>,
+    pub steps_completed: usize,
+    pub steps_total: usize,
+    /// Failed builds since the last success
+    pub consecutive_failures: u32,
+    /// The current failure has been acknowledged; cleared by the next success
+    pub acknowledged: bool,
+    /// Last status that wasn't a build in progress
+    #[serde(skip)]
+    pub settled_status: Option<String>,
+    #[serde(skip)]
+    pub rerun_requests: Vec<RerunRequest>,
+}
+
+/// A past build to repeat against its original commit
+#[derive(Debug, Clone)]
+pub struct RerunRequest {
+    pub build_id: u64,
+    pub commit_hash: String,
+    pub subproject: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -36,16 +198,161 @@ pub struct RepoInfo {
     pub path: String,
     pub branch: String,
     pub last_commit: String,
-    pub commands: Vec<String>,
+    pub commands: Vec<CommandSpec>,
     pub project_type: String,
+    pub dirty: bool,
+    pub changed_files: usize,
+    /// Commits on HEAD not on its upstream, None without an upstream branch
+    pub ahead: Option<u32>,
+    /// Commits on the upstream branch not on HEAD
+    pub behind: Option<u32>,
+    /// Latest commit left unbuilt because of a skip marker or path filter
+    pub skipped_commit: Option<SkippedCommit>,
+}
+
+/// Summary of a repository whose latest build is not green
+#[derive(Debug, Clone, Serialize)]
+pub struct FailingRepository {
+    pub name: String,
+    pub status: String,
+    pub last_build_id: Option<u64>,
+    pub last_commit: String,
+    pub acknowledged: bool,
+}
+
+/// Aggregate state of a project's member repositories
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectStatus {
+    pub name: String,
+    /// Worst status among the members, without any error detail
+    pub status: String,
+    pub repositories: Vec<ProjectMember>,
+    pub building: usize,
+    /// Failing members that haven't been acknowledged
+    pub failing: usize,
+    pub total_builds: usize,
+    /// Percentage of the members' recent builds that passed
+    pub success_rate: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectMember {
+    pub name: String,
+    pub status: String,
+    pub is_building: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedCommit {
+    pub commit_hash: String,
+    pub reason: String,
+}
+
+impl BuildComparison {
+    pub fn new(a: BuildResult, b: BuildResult) -> Self {
+        // Keep the order commands ran in, appending any that only exist in `b`
+        let mut names: Vec<&str> = a.steps.iter().map(|s| s.command.as_str()).collect();
+        for step in &b.steps {
+            if !names.contains(&step.command.as_str()) {
+                names.push(&step.command);
+            }
+        }
+        
+        let commands = names
+            .into_iter()
+            .map(|name| {
+                let step_a = a.steps.iter().find(|s| s.command == name);
+                let step_b = b.steps.iter().find(|s| s.command == name);
+                CommandComparison {
+                    name: step_a.or(step_b).map(|s| s.name.clone()).unwrap_or_default(),
+                    command: name.to_string(),
+                    a_success: step_a.map(|s| s.success),
+                    b_success: step_b.map(|s| s.success),
+                    success_changed: step_a.map(|s| s.success) != step_b.map(|s| s.success),
+                    duration_delta_ms: match (step_a, step_b) {
+                        (Some(sa), Some(sb)) => Some(sb.duration_ms as i64 - sa.duration_ms as i64),
+                        _ => None,
+                    },
+                }
+            })
+            .collect();
+        
+        Self {
+            success_changed: a.success != b.success,
+            duration_delta_ms: b.duration_ms as i64 - a.duration_ms as i64,
+            commands,
+            a,
+            b,
+        }
+    }
+}
+
+// Commit hash characters shown in logs and notifications
+pub const SHORT_HASH_LEN: usize = 8;
+
+/// The first `len` characters of a commit hash, or all of it when shorter
+pub fn abbreviate(hash: &str, len: usize) -> &str {
+    hash.get(..len).unwrap_or(hash)
+}
+
+pub fn short_hash(hash: &str) -> &str {
+    abbreviate(hash, SHORT_HASH_LEN)
 }
 
+// Recent builds counted towards a repository's health score
+const HEALTH_BUILDS: usize = 10;
+
+// Successful builds averaged for the duration baseline, and the fewest worth comparing to
+const BASELINE_BUILDS: usize = 10;
+const MIN_BASELINE_BUILDS: usize = 3;
+
 impl GlobalState {
     pub fn new() -> Self {
         Self {
             repositories: HashMap::new(),
             recent_builds: Vec::new(),
+            started_at: SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs(),
+            build_queue: BuildQueue::default(),
+            build_slots: Arc::default(),
+            last_build_id: 0,
+            store: None,
+            max_build_age: None,
+            paused: false,
+            events: EventLog::default(),
+        }
+    }
+    
+    /// Persists builds to `store` and seeds history and ids from it
+    pub fn attach_store(&mut self, store: BuildStore) {
+        self.last_build_id = store.last_build_id();
+        self.recent_builds = store.recent(None, 100, 0);
+        self.store = Some(Arc::new(store));
+        self.prune_builds();
+    }
+    
+    pub fn set_max_build_age(&mut self, max_age: Option<Duration>) {
+        self.max_build_age = max_age;
+    }
+    
+    pub fn set_paused(&mut self, paused: bool) {
+        if self.paused != paused {
+            let (kind, message) = if paused {
+                ("daemon_paused", "Builds paused")
+            } else {
+                ("daemon_resumed", "Builds resumed")
+            };
+            self.record_event(kind, None, message);
         }
+        self.paused = paused;
+    }
+    
+    pub fn record_event(&mut self, kind: &str, repository: Option<&str>, message: &str) {
+        self.events.record(Event::new(kind, repository, message));
+    }
+    
+    pub fn uptime_secs(&self) -> u64 {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        now.saturating_sub(self.started_at)
     }
     
     pub fn add_repository_state(&mut self, repository: Repository) {
@@ -55,41 +362,392 @@ impl GlobalState {
             last_commit: "unknown".to_string(),
             commands: repository.commands.clone(),
             project_type: format!("{:?}", repository.project_type),
+            dirty: false,
+            changed_files: 0,
+            ahead: None,
+            behind: None,
+            skipped_commit: None,
+        };
+        
+        let (builds, build_count) = match self.store {
+            Some(ref store) => (store.recent(Some(&repository.id), 50, 0), store.build_count(&repository.id)),
+            // Only builds interrupted by the previous run are known at this point
+            None => {
+                let builds: Vec<BuildResult> = self.recent_builds
+                    .iter()
+                    .filter(|build| build.repository_id == repository.id)
+                    .cloned()
+                    .collect();
+                let count = builds.iter().map(|build| build.repo_build_number).max().unwrap_or(0);
+                (builds, count)
+            }
         };
         
         let state = RepositoryState {
             repository: repository.clone(),
-            builds: Vec::new(),
+            builds,
             current_status: "Starting...".to_string(),
             repo_info,
+            build_requested: false,
+            webhook_received: false,
+            build_count,
+            is_building: false,
+            active_build_id: None,
+            progress: None,
+            steps_completed: 0,
+            steps_total: 0,
+            consecutive_failures: 0,
+            acknowledged: false,
+            settled_status: None,
+            rerun_requests: Vec::new(),
         };
         
         self.repositories.insert(repository.id, state);
     }
     
+    /// Continues build numbering from a previous run, never going backwards
+    pub fn restore_build_count(&mut self, repo_id: &Uuid, build_count: u64) {
+        if let Some(repo_state) = self.repositories.get_mut(repo_id) {
+            repo_state.build_count = repo_state.build_count.max(build_count);
+        }
+    }
+    
+    /// Allocates a daemon-wide build id and the repository's next build number
+    pub fn allocate_build(&mut self, repo_id: &Uuid) -> (u64, u64) {
+        self.last_build_id += 1;
+        let number = match self.repositories.get_mut(repo_id) {
+            Some(repo_state) => {
+                repo_state.build_count += 1;
+                repo_state.build_count
+            }
+            None => 0,
+        };
+        (self.last_build_id, number)
+    }
+    
+    /// Average duration of the last few successful builds of a repository
+    /// target, or None until there are enough of them to compare against
+    pub fn duration_baseline(&self, repo_id: &Uuid, subproject: Option<&str>) -> Option<u64> {
+        let repo_state = self.repositories.get(repo_id)?;
+        let durations: Vec<u64> = repo_state.builds
+            .iter()
+            .filter(|build| build.success && build.subproject.as_deref() == subproject)
+            .take(BASELINE_BUILDS)
+            .map(|build| build.duration_ms)
+            .collect();
+        if durations.len() < MIN_BASELINE_BUILDS {
+            return None;
+        }
+        Some(durations.iter().sum::<u64>() / durations.len() as u64)
+    }
+    
+    /// Updates the failure streak and returns its new length
+    pub fn record_build_outcome(&mut self, repo_id: &Uuid, success: bool) -> u32 {
+        match self.repositories.get_mut(repo_id) {
+            Some(repo_state) => {
+                repo_state.consecutive_failures = if success { 0 } else { repo_state.consecutive_failures + 1 };
+                repo_state.acknowledged &= !success;
+                repo_state.consecutive_failures
+            }
+            None => 0,
+        }
+    }
+    
+    pub fn set_active_build(&mut self, repo_id: &Uuid, build_id: Option<u64>) {
+        if let Some(repo_state) = self.repositories.get_mut(repo_id) {
+            repo_state.is_building = build_id.is_some();
+            repo_state.active_build_id = build_id;
+            if build_id.is_none() {
+                repo_state.progress = None;
+                repo_state.steps_completed = 0;
+                repo_state.steps_total = 0;
+            }
+        }
+    }
+    
+    pub fn set_build_progress(&mut self, repo_id: &Uuid, progress: f32, steps_completed: usize, steps_total: usize) {
+        if let Some(repo_state) = self.repositories.get_mut(repo_id) {
+            repo_state.progress = Some(progress);
+            repo_state.steps_completed = steps_completed;
+            repo_state.steps_total = steps_total;
+        }
+    }
+    
+    /// Step durations from the last successful build of a repository target
+    pub fn last_step_durations(&self, repo_id: &Uuid, subproject: Option<&str>) -> HashMap<String, u64> {
+        self.repositories
+            .get(repo_id)
+            .and_then(|repo_state| {
+                repo_state.builds
+                    .iter()
+                    .find(|build| build.success && build.subproject.as_deref() == subproject)
+            })
+            .map(|build| build.steps.iter().map(|step| (step.name.clone(), step.duration_ms)).collect())
+            .unwrap_or_default()
+    }
+    
     pub fn add_build(&mut self, build: BuildResult) {
+        if let Some(ref store) = self.store
+            && let Err(e) = store.insert(&build)
+        {
+            logging::warn(Some(&build.repository_name), "build_store_failed", &format!("Failed to store build #{}: {}", build.id, e));
+        }
+        
         // Add to repository-specific builds
         if let Some(repo_state) = self.repositories.get_mut(&build.repository_id) {
             repo_state.builds.insert(0, build.clone());
-            
-            // Keep only last 50 builds per repository
-            if repo_state.builds.len() > 50 {
-                repo_state.builds.truncate(50);
-            }
         }
         
         // Add to global recent builds
         self.recent_builds.insert(0, build);
-        
-        // Keep only last 100 recent builds globally
-        if self.recent_builds.len() > 100 {
+        self.prune_builds();
+    }
+    
+    // Lists are newest first, so pruning trims their tails
+    fn prune_builds(&mut self) {
+        let Some(max_age) = self.max_build_age else {
+            // Keep only the last 50 builds per repository and 100 overall
+            for repo_state in self.repositories.values_mut() {
+                repo_state.builds.truncate(50);
+            }
             self.recent_builds.truncate(100);
+            return;
+        };
+        
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let cutoff = now.saturating_sub(max_age.as_secs());
+        let expired = |builds: &mut Vec<BuildResult>| {
+            while builds.last().is_some_and(|build| build.timestamp < cutoff) {
+                builds.pop();
+            }
+        };
+        for repo_state in self.repositories.values_mut() {
+            expired(&mut repo_state.builds);
+        }
+        expired(&mut self.recent_builds);
+        
+        if let Some(ref store) = self.store
+            && let Err(e) = store.delete_before(cutoff)
+        {
+            logging::warn(None, "build_store_failed", &format!("Failed to prune old builds: {}", e));
+        }
+    }
+    
+    pub fn project_status(&self, project: &Project) -> ProjectStatus {
+        let mut members: Vec<&RepositoryState> = self.repositories
+            .values()
+            .filter(|rs| project.contains(&rs.repository))
+            .collect();
+        members.sort_by(|a, b| {
+            a.repository.order.cmp(&b.repository.order)
+                .then_with(|| a.repository.name.cmp(&b.repository.name))
+        });
+        
+        let status = match members.iter().max_by_key(|rs| rs.severity()) {
+            Some(worst) if worst.severity() == 2 => "Building".to_string(),
+            Some(worst) => worst.current_status.split(':').next().unwrap_or_default().to_string(),
+            None => "Unknown".to_string(),
+        };
+        let total_builds = members.iter().map(|rs| rs.builds.len()).sum();
+        let passed: usize = members.iter().map(|rs| rs.builds.iter().filter(|build| build.success).count()).sum();
+        
+        ProjectStatus {
+            name: project.name.clone(),
+            status,
+            building: members.iter().filter(|rs| rs.is_building).count(),
+            failing: members.iter().filter(|rs| rs.is_failing() && !rs.acknowledged).count(),
+            total_builds,
+            success_rate: (total_builds > 0).then(|| (passed * 100 / total_builds) as u32),
+            repositories: members
+                .iter()
+                .map(|rs| ProjectMember {
+                    name: rs.repository.name.clone(),
+                    status: rs.current_status.clone(),
+                    is_building: rs.is_building,
+                })
+                .collect(),
+        }
+    }
+    
+    /// Records builds a previous run never finished, keeping their ids taken
+    pub fn add_interrupted_builds(&mut self, mut builds: Vec<BuildResult>) {
+        builds.sort_by_key(|build| build.id);
+        for build in builds {
+            self.last_build_id = self.last_build_id.max(build.id);
+            self.add_build(build);
+        }
+    }
+    
+    pub fn find_build(&self, id: u64) -> Option<BuildResult> {
+        self.recent_builds
+            .iter()
+            .find(|b| b.id == id)
+            .cloned()
+            .or_else(|| self.store.as_ref().and_then(|store| store.get(id)))
+    }
+    
+    pub fn set_build_note(&mut self, id: u64, note: Option<String>) -> bool {
+        let mut found = false;
+        let repo_builds = self.repositories.values_mut().flat_map(|rs| rs.builds.iter_mut());
+        for build in self.recent_builds.iter_mut().chain(repo_builds).filter(|b| b.id == id) {
+            build.note = note.clone();
+            found = true;
+        }
+        
+        if let Some(ref store) = self.store
+            && let Some(mut build) = store.get(id)
+        {
+            build.note = note;
+            if let Err(e) = store.insert(&build) {
+                logging::warn(Some(&build.repository_name), "build_store_failed", &format!("Failed to store note for build #{}: {}", id, e));
+            }
+            found = true;
+        }
+        found
+    }
+    
+    /// Newest first, optionally only builds of commits starting with `commit`;
+    /// reaches past the in-memory window when a store is attached
+    pub fn list_builds(&self, commit: Option<&str>, limit: usize, offset: usize) -> Vec<BuildResult> {
+        match (&self.store, commit) {
+            (Some(store), Some(prefix)) => store.recent_for_commit(prefix, limit, offset),
+            (Some(store), None) => store.recent(None, limit, offset),
+            (None, _) => self.recent_builds
+                .iter()
+                .filter(|build| commit.is_none_or(|prefix| build.commit_hash.starts_with(prefix)))
+                .skip(offset)
+                .take(limit)
+                .cloned()
+                .collect(),
         }
     }
     
     pub fn update_repository_status(&mut self, repo_id: &Uuid, status: String) {
+        let Some(repo_state) = self.repositories.get_mut(repo_id) else {
+            return;
+        };
+        // Only settled statuses make the event log, so every build doesn't
+        // show up as a pair of changes
+        let mut change = None;
+        if !matches!(status.as_str(), "Building..." | "Waiting for build slot")
+            && let Some(previous) = repo_state.settled_status.replace(status.clone())
+            && previous != status
+        {
+            change = Some((repo_state.repository.name.clone(), format!("{} → {}", previous, status)));
+        }
+        repo_state.current_status = status;
+        if let Some((name, message)) = change {
+            self.record_event("status_changed", Some(&name), &message);
+        }
+    }
+    
+    /// Refreshes the definition shown for a repository after the runner changed it
+    pub fn update_repository_definition(&mut self, repository: &Repository) {
+        if let Some(repo_state) = self.repositories.get_mut(&repository.id) {
+            repo_state.repo_info.project_type = format!("{:?}", repository.project_type);
+            repo_state.repo_info.commands = repository.commands.clone();
+            repo_state.repository = repository.clone();
+        }
+    }
+    
+    pub fn set_repository_enabled(&mut self, repo_id: &Uuid, enabled: bool) {
+        if let Some(repo_state) = self.repositories.get_mut(repo_id) {
+            repo_state.repository.enabled = enabled;
+        }
+    }
+    
+    /// Marks a failing repository as acknowledged; false if it isn't failing
+    pub fn acknowledge(&mut self, repo_id: &Uuid) -> bool {
+        match self.repositories.get_mut(repo_id) {
+            Some(repo_state) if repo_state.is_failing() => {
+                repo_state.acknowledged = true;
+                let name = repo_state.repository.name.clone();
+                self.record_event("failure_acknowledged", Some(&name), "Failure acknowledged");
+                true
+            }
+            _ => false,
+        }
+    }
+    
+    pub fn request_build(&mut self, repo_id: &Uuid) -> bool {
+        match self.repositories.get_mut(repo_id) {
+            Some(repo_state) if repo_state.repository.enabled => {
+                repo_state.build_requested = true;
+                true
+            }
+            _ => false,
+        }
+    }
+    
+    pub fn request_all_builds(&mut self) -> usize {
+        let repo_ids: Vec<Uuid> = self.repositories.keys().cloned().collect();
+        repo_ids.iter().filter(|id| self.request_build(id)).count()
+    }
+    
+    pub fn notify_webhook(&mut self, repo_id: &Uuid) -> bool {
+        match self.repositories.get_mut(repo_id) {
+            Some(repo_state) if repo_state.repository.enabled => {
+                repo_state.webhook_received = true;
+                let name = repo_state.repository.name.clone();
+                self.record_event("webhook_received", Some(&name), "Push webhook received");
+                true
+            }
+            _ => false,
+        }
+    }
+    
+    pub fn take_webhook(&mut self, repo_id: &Uuid) -> bool {
+        match self.repositories.get_mut(repo_id) {
+            Some(repo_state) => std::mem::take(&mut repo_state.webhook_received),
+            None => false,
+        }
+    }
+    
+    pub fn take_build_request(&mut self, repo_id: &Uuid) -> bool {
+        match self.repositories.get_mut(repo_id) {
+            Some(repo_state) => std::mem::take(&mut repo_state.build_requested),
+            None => false,
+        }
+    }
+    
+    pub fn request_rerun(&mut self, build: &BuildResult) -> bool {
+        match self.repositories.get_mut(&build.repository_id) {
+            Some(repo_state) if repo_state.repository.enabled => {
+                repo_state.rerun_requests.push(RerunRequest {
+                    build_id: build.id,
+                    commit_hash: build.commit_hash.clone(),
+                    subproject: build.subproject.clone(),
+                });
+                true
+            }
+            _ => false,
+        }
+    }
+    
+    pub fn take_rerun_requests(&mut self, repo_id: &Uuid) -> Vec<RerunRequest> {
+        match self.repositories.get_mut(repo_id) {
+            Some(repo_state) => std::mem::take(&mut repo_state.rerun_requests),
+            None => Vec::new(),
+        }
+    }
+    
+    pub fn update_working_tree(&mut self, repo_id: &Uuid, changed_files: usize) {
         if let Some(repo_state) = self.repositories.get_mut(repo_id) {
-            repo_state.current_status = status;
+            repo_state.repo_info.dirty = changed_files > 0;
+            repo_state.repo_info.changed_files = changed_files;
+        }
+    }
+    
+    pub fn update_upstream(&mut self, repo_id: &Uuid, ahead_behind: Option<(u32, u32)>) {
+        if let Some(repo_state) = self.repositories.get_mut(repo_id) {
+            repo_state.repo_info.ahead = ahead_behind.map(|(ahead, _)| ahead);
+            repo_state.repo_info.behind = ahead_behind.map(|(_, behind)| behind);
+        }
+    }
+    
+    pub fn set_skipped_commit(&mut self, repo_id: &Uuid, skipped: Option<SkippedCommit>) {
+        if let Some(repo_state) = self.repositories.get_mut(repo_id) {
+            repo_state.repo_info.skipped_commit = skipped;
         }
     }
     
@@ -111,10 +769,91 @@ impl RepositoryState {
                 last_commit: "unknown".to_string(),
                 commands: repository.commands.clone(),
                 project_type: format!("{:?}", repository.project_type),
+                dirty: false,
+                changed_files: 0,
+                ahead: None,
+                behind: None,
+                skipped_commit: None,
             },
             repository,
             builds: Vec::new(),
             current_status: "Starting...".to_string(),
+            build_requested: false,
+            webhook_received: false,
+            build_count: 0,
+            is_building: false,
+            active_build_id: None,
+            progress: None,
+            steps_completed: 0,
+            steps_total: 0,
+            consecutive_failures: 0,
+            acknowledged: false,
+            settled_status: None,
+            rerun_requests: Vec::new(),
+        }
+    }
+    
+    pub fn is_failing(&self) -> bool {
+        matches!(self.current_status.as_str(), "Failed" | "Unstable" | "NoCommands")
+            || self.current_status.starts_with("Error")
+            || self.current_status.starts_with("AuthError")
+    }
+    
+    /// 0 (broken) to 100 (healthy): the success rate of recent builds, newer
+    /// ones weighing more, averaged with the current status where it says
+    /// something about health
+    pub fn health_score(&self) -> u32 {
+        let (passed, total) = self.builds
+            .iter()
+            .filter(|build| !build.superseded && !build.interrupted)
+            .take(HEALTH_BUILDS)
+            .enumerate()
+            .fold((0.0, 0.0), |(passed, total), (index, build)| {
+                let weight = (HEALTH_BUILDS - index) as f64;
+                (passed + if build.success { weight } else { 0.0 }, total + weight)
+            });
+        let history = if total > 0.0 { Some(passed / total) } else { None };
+        
+        let status = match self.current_status.as_str() {
+            "Passing" => Some(1.0),
+            "Unstable" => Some(0.5),
+            "Failed" | "NoCommands" => Some(0.0),
+            status if status.starts_with("Error") || status.starts_with("AuthError") => Some(0.0),
+            _ => None,
+        };
+        
+        let health = match (history, status) {
+            (Some(history), Some(status)) => (history + status) / 2.0,
+            (Some(score), None) | (None, Some(score)) => score,
+            (None, None) => 1.0,
+        };
+        (health * 100.0).round() as u32
+    }
+    
+    // How bad the status is when rolled up into a project; acknowledged
+    // failures count as passing
+    fn severity(&self) -> u8 {
+        if self.acknowledged {
+            return 1;
+        }
+        match self.current_status.as_str() {
+            status if status.starts_with("Error") || status.starts_with("AuthError") => 5,
+            "NoCommands" => 5,
+            "Failed" => 4,
+            "Unstable" => 3,
+            _ if self.is_building => 2,
+            "Passing" => 1,
+            _ => 0,
+        }
+    }
+    
+    pub fn failing_summary(&self) -> FailingRepository {
+        FailingRepository {
+            name: self.repository.name.clone(),
+            status: self.current_status.clone(),
+            last_build_id: self.builds.first().map(|build| build.id),
+            last_commit: self.repo_info.last_commit.clone(),
+            acknowledged: self.acknowledged,
         }
     }
 }
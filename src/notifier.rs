@@ -0,0 +1,157 @@
+use crate::config::{NotifierConfig, NotifierKind};
+use crate::models::BuildResult;
+use crate::logging;
+use std::time::Duration;
+
+// Upper bound on the log excerpt so messages stay within chat service limits
+const MAX_TAIL_CHARS: usize = 3000;
+
+pub struct Notifier {
+    config: NotifierConfig,
+    client: reqwest::Client,
+}
+
+impl Notifier {
+    pub fn new(config: NotifierConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        
+        Self { config, client }
+    }
+    
+    pub fn name(&self) -> &str {
+        &self.config.name
+    }
+    
+    pub async fn send(&self, build: &BuildResult, message: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let payload = match self.config.kind {
+            NotifierKind::Slack => serde_json::json!({ "text": message }),
+            NotifierKind::Webhook => serde_json::json!({
+                "message": message,
+                "repository": build.repository_name,
+                "build_id": build.id,
+                "success": build.success,
+                "commit_hash": build.commit_hash,
+            }),
+        };
+        
+        let response = self.client.post(&self.config.url).json(&payload).send().await?;
+        if !response.status().is_success() {
+            return Err(format!("Notifier responded with {}", response.status()).into());
+        }
+        
+        Ok(())
+    }
+    
+    /// Reports the daemon itself coming up or going down, rather than a build
+    pub async fn send_lifecycle(&self, event: &str, message: &str) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let payload = match self.config.kind {
+            NotifierKind::Slack => serde_json::json!({ "text": message }),
+            NotifierKind::Webhook => serde_json::json!({
+                "message": message,
+                "event": event,
+                "hostname": hostname(),
+                "version": env!("CARGO_PKG_VERSION"),
+            }),
+        };
+        
+        let response = self.client.post(&self.config.url).json(&payload).send().await?;
+        if !response.status().is_success() {
+            return Err(format!("Notifier responded with {}", response.status()).into());
+        }
+        
+        Ok(())
+    }
+}
+
+pub fn hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        let result = unsafe { libc::gethostname(buffer.as_mut_ptr() as *mut libc::c_char, buffer.len()) };
+        if result == 0 {
+            let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+            return String::from_utf8_lossy(&buffer[..len]).into_owned();
+        }
+    }
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// Returns the last `lines` lines of the failing command's section of the
+/// (already redacted) build output, or of the whole output if no step failed.
+pub fn failure_tail(build: &BuildResult, lines: usize) -> Option<String> {
+    if lines == 0 || build.output.is_empty() {
+        return None;
+    }
+    
+    let section = build.steps
+        .iter()
+        .find(|step| !step.success)
+        .and_then(|step| {
+            let header = format!("=== {} ===\n", step.name);
+            let start = build.output.find(&header)? + header.len();
+            let rest = &build.output[start..];
+            let end = rest.find("\n=== ").map(|i| i + 1).unwrap_or(rest.len());
+            Some(&rest[..end])
+        })
+        .unwrap_or(&build.output);
+    
+    let all_lines: Vec<&str> = section.trim_end().lines().collect();
+    let tail = all_lines[all_lines.len().saturating_sub(lines)..].join("\n");
+    if tail.trim().is_empty() {
+        return None;
+    }
+    
+    // Keep the end of the log, which is where errors usually are
+    let char_count = tail.chars().count();
+    if char_count > MAX_TAIL_CHARS {
+        let skipped: String = tail.chars().skip(char_count - MAX_TAIL_CHARS).collect();
+        return Some(format!("…{}", skipped));
+    }
+    
+    Some(tail)
+}
+
+// Attempts at an outcome webhook, and the wait before the first retry, which doubles after each
+const OUTCOME_WEBHOOK_ATTEMPTS: u32 = 3;
+const OUTCOME_WEBHOOK_BACKOFF: Duration = Duration::from_secs(2);
+
+/// POSTs the build result JSON to a repository's `on_success_webhook` or
+/// `on_failure_webhook` from a task of its own, retrying with backoff, so
+/// a slow or unreachable endpoint never holds up the runner
+pub fn send_outcome_webhook(url: String, build: BuildResult) {
+    tokio::spawn(async move {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .unwrap_or_else(|_| reqwest::Client::new());
+        let label = build.repository_name.clone();
+        let mut backoff = OUTCOME_WEBHOOK_BACKOFF;
+        
+        for attempt in 1..=OUTCOME_WEBHOOK_ATTEMPTS {
+            let error = match client.post(&url).json(&build).send().await {
+                Ok(response) if response.status().is_success() => {
+                    logging::info(Some(&label), "outcome_webhook_sent", &format!("🪝 Sent build #{} to {}", build.id, url));
+                    return;
+                }
+                Ok(response) => format!("HTTP {}", response.status()),
+                Err(e) => e.to_string(),
+            };
+            if attempt == OUTCOME_WEBHOOK_ATTEMPTS {
+                logging::error(Some(&label), "outcome_webhook_failed", &format!(
+                    "Giving up on sending build #{} to {} after {} attempts: {}", build.id, url, attempt, error
+                ));
+                return;
+            }
+            logging::warn(Some(&label), "outcome_webhook_failed", &format!(
+                "Sending build #{} to {} failed ({}); retrying in {}s", build.id, url, error, backoff.as_secs()
+            ));
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+        }
+    });
+}
@@ -4,61 +4,122 @@ use std::path::Path;
 
 pub struct ProjectDetector;
 
+/// A file that suggests a project type
+#[derive(Debug, Clone)]
+pub struct Indicator {
+    pub file: String,
+    pub project_type: ProjectType,
+}
+
+/// What detection found in a directory and which type it settled on
+#[derive(Debug, Clone)]
+pub struct Detection {
+    pub project_type: ProjectType,
+    /// Every indicator found, in precedence order
+    pub indicators: Vec<Indicator>,
+    pub reason: String,
+}
+
+// Types in the order they win when a repository matches several
+const PRECEDENCE: [ProjectType; 5] = [
+    ProjectType::Rust,
+    ProjectType::Python,
+    ProjectType::Deno,
+    ProjectType::Bun,
+    ProjectType::Node,
+];
+
+const PYTHON_FILES: [&str; 7] = [
+    "requirements.txt",
+    "setup.py",
+    "pyproject.toml",
+    "Pipfile",
+    "poetry.lock",
+    "pytest.ini",
+    "tox.ini",
+];
+
 impl ProjectDetector {
     pub fn new() -> Self {
         Self
     }
     
-    pub fn detect_project_type(&self, path: &str) -> ProjectType {
-        let project_path = Path::new(path);
+    pub fn detect_project_type(&self, path: &str) -> Detection {
+        let indicators = self.find_indicators(Path::new(path));
         
-        // Check for Rust project
-        if project_path.join("Cargo.toml").exists() {
-            return ProjectType::Rust;
-        }
+        let Some(chosen) = PRECEDENCE.iter().find(|project_type| indicators.iter().any(|i| i.project_type == **project_type)) else {
+            return Detection {
+                project_type: ProjectType::Generic,
+                indicators,
+                reason: "no project files found".to_string(),
+            };
+        };
         
-        // Check for Python project
-        if self.has_python_indicators(project_path) {
-            return ProjectType::Python;
+        let files: Vec<&str> = indicators
+            .iter()
+            .filter(|i| i.project_type == *chosen)
+            .map(|i| i.file.as_str())
+            .collect();
+        let mut reason = format!("{} found", files.join(", "));
+        let mut others: Vec<String> = Vec::new();
+        for indicator in &indicators {
+            let other = format!("{:?}", indicator.project_type);
+            if indicator.project_type != *chosen && !others.contains(&other) {
+                others.push(other);
+            }
         }
-        
-        // Check for Node.js project
-        if project_path.join("package.json").exists() {
-            return ProjectType::Node;
+        if !others.is_empty() {
+            reason.push_str(&format!("; {:?} takes precedence over {}", chosen, others.join(", ")));
         }
         
-        ProjectType::Generic
+        Detection {
+            project_type: chosen.clone(),
+            indicators,
+            reason,
+        }
     }
     
-    fn has_python_indicators(&self, path: &Path) -> bool {
-        // Check for common Python project files
-        let python_files = [
-            "requirements.txt",
-            "setup.py",
-            "pyproject.toml",
-            "Pipfile",
-            "poetry.lock",
-            "pytest.ini",
-            "tox.ini",
-        ];
-        
-        for file in &python_files {
+    fn find_indicators(&self, path: &Path) -> Vec<Indicator> {
+        let mut indicators = Vec::new();
+        let mut found = |file: &str, project_type: ProjectType| {
             if path.join(file).exists() {
-                return true;
+                indicators.push(Indicator { file: file.to_string(), project_type });
             }
+        };
+        
+        found("Cargo.toml", ProjectType::Rust);
+        for file in PYTHON_FILES {
+            found(file, ProjectType::Python);
+        }
+        found("deno.json", ProjectType::Deno);
+        found("deno.jsonc", ProjectType::Deno);
+        // Bun's lockfile only counts alongside a package.json
+        if path.join("package.json").exists() {
+            found("bun.lockb", ProjectType::Bun);
+            found("bun.lock", ProjectType::Bun);
+            found("package.json", ProjectType::Node);
         }
         
-        // Check for Python source files
-        if let Ok(entries) = fs::read_dir(path) {
-            for entry in entries.flatten() {
-                if let Some(ext) = entry.path().extension() {
-                    if ext == "py" {
-                        return true;
-                    }
-                }
-            }
+        // Python source files count when no Python project file does
+        if !indicators.iter().any(|i| i.project_type == ProjectType::Python)
+            && let Some(source) = self.find_python_source(path)
+        {
+            let position = indicators.iter().position(|i| i.project_type != ProjectType::Rust).unwrap_or(indicators.len());
+            indicators.insert(position, Indicator { file: source, project_type: ProjectType::Python });
         }
         
-        false
+        indicators
+    }
+    
+    fn find_python_source(&self, path: &Path) -> Option<String> {
+        let entries = fs::read_dir(path).ok()?;
+        let mut sources: Vec<String> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "py"))
+            .filter_map(|path| path.file_name().map(|name| name.to_string_lossy().to_string()))
+            .collect();
+        sources.sort();
+        sources.into_iter().next()
     }
 }
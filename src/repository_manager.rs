@@ -1,9 +1,15 @@
-use crate::config::{Config, Repository};
+use crate::config::{Config, ProjectType, Repository};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
+use std::path::Path;
+use std::process::Command;
+use std::sync::Mutex;
 use uuid::Uuid;
 
+// Runners on different worker threads can write to the repository list at once
+static FILE_LOCK: Mutex<()> = Mutex::new(());
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RepositoryManager {
     repositories: HashMap<Uuid, Repository>,
@@ -28,7 +34,18 @@ impl RepositoryManager {
         Ok(())
     }
     
-    pub fn add_repository(&mut self, path: String, name: Option<String>) -> Result<Repository, Box<dyn std::error::Error>> {
+    /// Applies `change` to one stored repository and saves the list right away
+    pub fn update_stored(config: &Config, id: &Uuid, change: impl FnOnce(&mut Repository)) -> Result<(), Box<dyn std::error::Error>> {
+        let _guard = FILE_LOCK.lock().unwrap();
+        let mut manager = Self::load(config)?;
+        let repo = manager.repositories
+            .get_mut(id)
+            .ok_or("Repository is no longer configured")?;
+        change(repo);
+        manager.save(config)
+    }
+    
+    pub fn add_repository(&mut self, path: String, name: Option<String>, project_type: Option<ProjectType>) -> Result<Repository, Box<dyn std::error::Error>> {
         // Check if repository with same path already exists
         for repo in self.repositories.values() {
             if repo.path == path {
@@ -36,13 +53,50 @@ impl RepositoryManager {
             }
         }
         
-        let repo = Repository::new(path, name)?;
+        let mut repo = Repository::new(path, name, project_type)?;
+        repo.order = self.next_order();
         let repo_clone = repo.clone();
         self.repositories.insert(repo.id, repo);
         
         Ok(repo_clone)
     }
     
+    // Clones `url` into `clone_to` and registers the clone; partial clones are removed on failure
+    pub fn add_remote_repository(&mut self, git: &str, url: String, clone_to: String, name: Option<String>, project_type: Option<ProjectType>) -> Result<Repository, Box<dyn std::error::Error>> {
+        if self.repositories.values().any(|repo| repo.path == clone_to) {
+            return Err(format!("Repository with path '{}' already exists", clone_to).into());
+        }
+        if Path::new(&clone_to).exists() {
+            return Err(format!("Clone destination already exists: {}", clone_to).into());
+        }
+        
+        let output = Command::new(git)
+            .args(["clone", &url, &clone_to])
+            .output()?;
+        
+        if !output.status.success() {
+            fs::remove_dir_all(&clone_to).ok();
+            return Err(format!(
+                "Failed to clone {}: {}",
+                url,
+                String::from_utf8_lossy(&output.stderr).trim()
+            ).into());
+        }
+        
+        let mut repo = match Repository::new(clone_to.clone(), name, project_type) {
+            Ok(repo) => repo,
+            Err(e) => {
+                fs::remove_dir_all(&clone_to).ok();
+                return Err(e);
+            }
+        };
+        repo.remote_url = Some(url);
+        repo.order = self.next_order();
+        self.repositories.insert(repo.id, repo.clone());
+        
+        Ok(repo)
+    }
+    
     pub fn remove_repository(&mut self, name: &str) -> bool {
         let repo_id = self.repositories
             .iter()
@@ -57,7 +111,76 @@ impl RepositoryManager {
         }
     }
     
+    pub fn export(&self, file: &str) -> Result<usize, Box<dyn std::error::Error>> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(file, content)?;
+        Ok(self.repositories.len())
+    }
+    
+    /// Merges repositories from an exported file, assigning fresh ids and
+    /// skipping paths that are already configured. With `base_path`, each
+    /// repository's directory name is re-rooted under it.
+    pub fn import(&mut self, file: &str, base_path: Option<&str>) -> Result<(Vec<Repository>, Vec<Repository>), Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(file)?;
+        let exported: RepositoryManager = serde_json::from_str(&content)?;
+        
+        let mut imported = Vec::new();
+        let mut skipped = Vec::new();
+        for mut repo in exported.get_repositories() {
+            if let Some(base) = base_path {
+                let dir_name = Path::new(&repo.path)
+                    .file_name()
+                    .map(|n| n.to_os_string())
+                    .unwrap_or_else(|| repo.name.clone().into());
+                repo.path = Path::new(base).join(dir_name).to_string_lossy().to_string();
+            }
+            
+            if self.repositories.values().any(|existing| existing.path == repo.path) {
+                skipped.push(repo);
+                continue;
+            }
+            
+            repo.id = Uuid::new_v4();
+            repo.order = self.next_order();
+            self.repositories.insert(repo.id, repo.clone());
+            imported.push(repo);
+        }
+        
+        Ok((imported, skipped))
+    }
+    
+    pub fn find_repository(&self, name: &str) -> Option<&Repository> {
+        self.repositories.values().find(|repo| repo.name == name)
+    }
+    
     pub fn get_repositories(&self) -> Vec<Repository> {
-        self.repositories.values().cloned().collect()
+        let mut repositories: Vec<Repository> = self.repositories.values().cloned().collect();
+        repositories.sort_by(|a, b| a.order.cmp(&b.order).then_with(|| a.name.cmp(&b.name)));
+        repositories
+    }
+    
+    fn next_order(&self) -> u32 {
+        self.repositories.values().map(|repo| repo.order + 1).max().unwrap_or(0)
+    }
+    
+    /// Moves a repository to a 1-based position and renumbers the rest
+    pub fn reorder_repository(&mut self, name: &str, position: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let mut ordered = self.get_repositories();
+        let index = ordered
+            .iter()
+            .position(|repo| repo.name == name)
+            .ok_or_else(|| format!("Repository '{}' not found", name))?;
+        
+        let repo = ordered.remove(index);
+        let target = position.saturating_sub(1).min(ordered.len());
+        ordered.insert(target, repo);
+        
+        for (order, repo) in ordered.iter().enumerate() {
+            if let Some(stored) = self.repositories.get_mut(&repo.id) {
+                stored.order = order as u32;
+            }
+        }
+        
+        Ok(())
     }
 }
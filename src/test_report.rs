@@ -0,0 +1,93 @@
+use crate::config::ProjectType;
+use crate::models::TestSummary;
+use regex::Regex;
+use std::sync::LazyLock;
+
+// `cargo test` prints one of these per test binary, doc tests included
+static CARGO_RESULT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"test result: \w+\. (\d+) passed; (\d+) failed; (\d+) ignored").unwrap()
+});
+
+// pytest's closing line, e.g. "==== 2 failed, 124 passed, 3 skipped in 1.52s ===="
+static PYTEST_SUMMARY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?m)^=+ (.+?) in [\d.]+s(?: \([^)]*\))? =+\s*$").unwrap()
+});
+static PYTEST_COUNT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(\d+) (passed|failed|errors?|skipped|xfailed|xpassed)").unwrap()
+});
+
+/// Test counts found in a build's output by the parser for its project
+/// type; None when the type has no parser or the output has no results
+pub fn parse(project_type: &ProjectType, output: &str) -> Option<TestSummary> {
+    match project_type {
+        ProjectType::Rust => parse_cargo(output),
+        ProjectType::Python => parse_pytest(output),
+        _ => None,
+    }
+}
+
+fn parse_cargo(output: &str) -> Option<TestSummary> {
+    let mut summary: Option<TestSummary> = None;
+    for captures in CARGO_RESULT.captures_iter(output) {
+        let count = |index: usize| captures[index].parse::<u32>().unwrap_or(0);
+        let total = summary.get_or_insert_with(TestSummary::default);
+        total.passed += count(1);
+        total.failed += count(2);
+        total.skipped += count(3);
+    }
+    summary
+}
+
+fn parse_pytest(output: &str) -> Option<TestSummary> {
+    // Several pytest runs in one build each print a summary line
+    let mut summary: Option<TestSummary> = None;
+    for line in PYTEST_SUMMARY.captures_iter(output) {
+        let total = summary.get_or_insert_with(TestSummary::default);
+        for captures in PYTEST_COUNT.captures_iter(&line[1]) {
+            let count = captures[1].parse::<u32>().unwrap_or(0);
+            match &captures[2] {
+                "passed" | "xpassed" => total.passed += count,
+                "failed" | "error" | "errors" => total.failed += count,
+                _ => total.skipped += count,
+            }
+        }
+    }
+    summary
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sums_cargo_results_of_every_test_binary() {
+        let output = "\
+running 12 tests
+test result: ok. 12 passed; 0 failed; 1 ignored; 0 measured; 0 filtered out; finished in 0.02s
+
+running 3 tests
+test result: FAILED. 1 passed; 2 failed; 0 ignored; 0 measured; 0 filtered out; finished in 0.01s
+
+   Doc-tests app
+test result: ok. 4 passed; 0 failed; 2 ignored; 0 measured; 0 filtered out; finished in 0.30s
+";
+        assert_eq!(parse(&ProjectType::Rust, output), Some(TestSummary { passed: 17, failed: 2, skipped: 3 }));
+    }
+
+    #[test]
+    fn counts_pytest_errors_as_failed() {
+        let output = "\
+tests/test_app.py .F.E
+=== 1 failed, 2 passed, 1 error in 0.52s ===
+";
+        assert_eq!(parse(&ProjectType::Python, output), Some(TestSummary { passed: 2, failed: 2, skipped: 0 }));
+    }
+
+    #[test]
+    fn output_without_summary_has_no_results() {
+        let output = "error: could not compile `app` due to 2 previous errors\n";
+        assert_eq!(parse(&ProjectType::Rust, output), None);
+        assert_eq!(parse(&ProjectType::Python, output), None);
+        assert_eq!(parse(&ProjectType::Node, "Tests: 3 passed, 3 total\n"), None);
+    }
+}
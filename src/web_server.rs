@@ -1,24 +1,152 @@
-use crate::models::{GlobalState};
-use std::sync::{Arc, Mutex};
-use warp::Filter;
+use crate::ci_runner;
+use crate::config::{self, Config};
+use crate::logging;
+use crate::models::{BuildComparison, GlobalState};
+use crate::webhook::{self, Provider};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use warp::http::StatusCode;
+use warp::{Filter, Reply};
 
 type SharedGlobalState = Arc<Mutex<GlobalState>>;
 
+// A failed API request, answered by `handle_rejection` with its status and
+// a `{"error": message}` body
+#[derive(Debug)]
+struct ApiError {
+    status: StatusCode,
+    message: String,
+}
+
+impl warp::reject::Reject for ApiError {}
+
+impl ApiError {
+    fn reject(status: StatusCode, message: impl Into<String>) -> warp::Rejection {
+        warp::reject::custom(ApiError { status, message: message.into() })
+    }
+    
+    fn not_found(message: impl Into<String>) -> warp::Rejection {
+        Self::reject(StatusCode::NOT_FOUND, message)
+    }
+    
+    fn bad_request(message: impl Into<String>) -> warp::Rejection {
+        Self::reject(StatusCode::BAD_REQUEST, message)
+    }
+    
+    /// The webhook signature or token doesn't match the repository's secret
+    fn unauthorized(message: impl Into<String>) -> warp::Rejection {
+        Self::reject(StatusCode::UNAUTHORIZED, message)
+    }
+    
+    fn forbidden(message: impl Into<String>) -> warp::Rejection {
+        Self::reject(StatusCode::FORBIDDEN, message)
+    }
+    
+    /// The request is valid but the repository or build isn't in a state to honour it
+    fn conflict(message: impl Into<String>) -> warp::Rejection {
+        Self::reject(StatusCode::CONFLICT, message)
+    }
+}
+
+// Turns rejections, ours and warp's, into the same JSON error envelope
+async fn handle_rejection(rejection: warp::Rejection) -> Result<impl warp::Reply, Infallible> {
+    let (status, message) = if let Some(error) = rejection.find::<ApiError>() {
+        (error.status, error.message.clone())
+    } else if rejection.is_not_found() {
+        (StatusCode::NOT_FOUND, "Not found".to_string())
+    } else if let Some(error) = rejection.find::<warp::reject::InvalidQuery>() {
+        (StatusCode::BAD_REQUEST, error.to_string())
+    } else if rejection.find::<warp::reject::LengthRequired>().is_some() {
+        (StatusCode::LENGTH_REQUIRED, "Content-Length header is required".to_string())
+    } else if rejection.find::<warp::reject::PayloadTooLarge>().is_some() {
+        (StatusCode::PAYLOAD_TOO_LARGE, "Request body is too large".to_string())
+    } else if rejection.find::<warp::reject::MethodNotAllowed>().is_some() {
+        (StatusCode::METHOD_NOT_ALLOWED, "Method not allowed".to_string())
+    } else {
+        logging::warn(None, "web_request_failed", &format!("Unhandled rejection: {:?}", rejection));
+        (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string())
+    };
+    Ok(warp::reply::with_status(warp::reply::json(&serde_json::json!({"error": message})), status))
+}
+
+#[derive(Debug, Deserialize)]
+struct BuildsQuery {
+    #[serde(default = "default_builds_limit")]
+    limit: usize,
+    #[serde(default)]
+    offset: usize,
+    /// Full commit hash or a prefix of at least MIN_COMMIT_PREFIX characters
+    commit: Option<String>,
+}
+
+// Shorter prefixes than git itself abbreviates to match too much to be useful
+const MIN_COMMIT_PREFIX: usize = 4;
+
+fn default_builds_limit() -> usize {
+    100
+}
+
+// Badge looks following shields.io, so badges sit well next to others in a README
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum BadgeStyle {
+    #[default]
+    Flat,
+    Plastic,
+    ForTheBadge,
+}
+
+#[derive(Debug, Deserialize)]
+struct BadgeQuery {
+    #[serde(default)]
+    style: BadgeStyle,
+    /// Left-hand text; defaults to the repository name
+    label: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    #[serde(default = "default_builds_limit")]
+    limit: usize,
+    /// Only events of this repository
+    repository: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompareQuery {
+    a: u64,
+    b: u64,
+}
+
 pub struct WebServer {
     global_state: SharedGlobalState,
     port: u16,
+    config: Config,
 }
 
 impl WebServer {
-    pub fn new(global_state: SharedGlobalState, port: u16) -> Self {
-        Self { global_state, port }
+    pub fn new(global_state: SharedGlobalState, config: Config) -> Self {
+        Self { global_state, port: config.web_port, config }
     }
     
-    pub async fn start(self) {
+    /// Binds the listening socket, returning the address it ended up on and
+    /// the future that serves requests
+    pub fn bind(self) -> Result<(SocketAddr, impl Future<Output = ()>), String> {
         let state_filter = warp::any().map(move || Arc::clone(&self.global_state));
+        let config = self.config.clone();
+        let config_filter = warp::any().map(move || config.clone());
+        
+        let api_config = warp::path!("api" / "config")
+            .and(warp::get())
+            .and(config_filter.clone())
+            .and_then(get_ui_config);
         
         let api_status = warp::path!("api" / "status")
             .and(warp::get())
+            .and(state_filter.clone())
             .and_then(get_status);
         
         let api_repositories = warp::path!("api" / "repositories")
@@ -26,71 +154,569 @@ impl WebServer {
             .and(state_filter.clone())
             .and_then(get_repositories);
         
+        let api_failing = warp::path!("api" / "repositories" / "failing")
+            .and(warp::get())
+            .and(state_filter.clone())
+            .and_then(get_failing_repositories);
+        
+        let api_projects = warp::path!("api" / "projects")
+            .and(warp::get())
+            .and(state_filter.clone())
+            .and(config_filter.clone())
+            .and_then(get_projects);
+        
         let api_repository = warp::path!("api" / "repository" / String)
             .and(warp::get())
             .and(state_filter.clone())
             .and_then(get_repository);
         
+        let api_badge = warp::path!("api" / "repository" / String / "badge.svg")
+            .and(warp::get())
+            .and(warp::query::<BadgeQuery>())
+            .and(state_filter.clone())
+            .and_then(get_badge);
+        
         let api_builds = warp::path!("api" / "builds")
             .and(warp::get())
+            .and(warp::query::<BuildsQuery>())
             .and(state_filter.clone())
             .and_then(get_recent_builds);
         
+        let api_compare = warp::path!("api" / "builds" / "compare")
+            .and(warp::get())
+            .and(warp::query::<CompareQuery>())
+            .and(state_filter.clone())
+            .and_then(compare_builds);
+        
         let api_build = warp::path!("api" / "build" / u64)
             .and(warp::get())
-            .and(state_filter)
+            .and(state_filter.clone())
             .and_then(get_build_detail);
         
+        let api_build_output = warp::path!("api" / "build" / u64 / "output")
+            .and(warp::get())
+            .and(state_filter.clone())
+            .and_then(get_build_output);
+        
+        let api_build_download = warp::path!("api" / "build" / u64 / "download")
+            .and(warp::get())
+            .and(state_filter.clone())
+            .and_then(download_build_log);
+        
+        let api_note = warp::path!("api" / "build" / u64 / "note")
+            .and(warp::post())
+            .and(warp::body::content_length_limit(MAX_NOTE_BYTES))
+            .and(warp::body::bytes())
+            .and(state_filter.clone())
+            .and_then(set_build_note);
+        
+        let api_rerun = warp::path!("api" / "build" / u64 / "rerun")
+            .and(warp::post())
+            .and(state_filter.clone())
+            .and(config_filter)
+            .and_then(rerun_build);
+        
+        let api_trigger = warp::path!("api" / "repository" / String / "trigger")
+            .and(warp::post())
+            .and(state_filter.clone())
+            .and_then(trigger_repository);
+        
+        let api_acknowledge = warp::path!("api" / "repository" / String / "acknowledge")
+            .and(warp::post())
+            .and(state_filter.clone())
+            .and_then(acknowledge_repository);
+        
+        let api_webhook = warp::path!("api" / "webhook" / String)
+            .and(warp::post())
+            .and(warp::header::headers_cloned())
+            .and(warp::body::content_length_limit(MAX_WEBHOOK_BYTES))
+            .and(warp::body::bytes())
+            .and(state_filter.clone())
+            .and_then(receive_webhook);
+        
+        let api_trigger_all = warp::path!("api" / "trigger-all")
+            .and(warp::post())
+            .and(state_filter.clone())
+            .and_then(trigger_all);
+        
+        let api_events = warp::path!("api" / "events" / "log")
+            .and(warp::get())
+            .and(warp::query::<EventsQuery>())
+            .and(state_filter.clone())
+            .and_then(get_events);
+        
+        let api_pause = warp::path!("api" / "pause")
+            .and(warp::post())
+            .and(state_filter.clone())
+            .and_then(|state| set_paused(state, true));
+        
+        let api_resume = warp::path!("api" / "resume")
+            .and(warp::post())
+            .and(state_filter)
+            .and_then(|state| set_paused(state, false));
+        
         let index = warp::path::end()
             .and(warp::get())
             .and_then(serve_index);
         
         let routes = index
             .or(api_status)
+            .or(api_config)
             .or(api_repositories)
+            .or(api_failing)
+            .or(api_projects)
             .or(api_repository)
+            .or(api_badge)
             .or(api_builds)
-            .or(api_build);
-
-        println!("🌐 Turbulent CI web interface available at http://localhost:{}", self.port);
+            .or(api_compare)
+            .or(api_build)
+            .or(api_build_output)
+            .or(api_build_download)
+            .or(api_rerun)
+            .or(api_note)
+            .or(api_trigger)
+            .or(api_acknowledge)
+            .or(api_webhook)
+            .or(api_trigger_all)
+            .or(api_events)
+            .or(api_pause)
+            .or(api_resume)
+            .recover(handle_rejection);
+
+        // With auto_port, try the following ports before giving up
+        let attempts = if self.config.auto_port { AUTO_PORT_ATTEMPTS } else { 1 };
+        for port in (self.port..).take(attempts) {
+            match warp::serve(routes.clone()).try_bind_ephemeral(([127, 0, 0, 1], port)) {
+                Ok((addr, server)) => {
+                    if port != self.port {
+                        logging::warn(None, "port_changed", &format!("Port {} is in use; bound to {} instead", self.port, port));
+                    }
+                    logging::info(None, "web_started", &format!("🌐 Turbulent CI web interface available at http://localhost:{}", addr.port()));
+                    return Ok((addr, server));
+                }
+                Err(e) if is_addr_in_use(&e) => continue,
+                Err(e) => return Err(format!("Failed to start web server on port {}: {}", port, e)),
+            }
+        }
         
-        warp::serve(routes)
-            .run(([127, 0, 0, 1], self.port))
-            .await;
+        Err(if self.config.auto_port {
+            format!("Ports {}-{} are all in use", self.port, self.port.saturating_add(attempts as u16 - 1))
+        } else {
+            format!("Port {} is already in use; stop the other process, pick another with --port, or pass --auto-port", self.port)
+        })
+    }
+}
+
+const AUTO_PORT_ATTEMPTS: usize = 10;
+
+fn is_addr_in_use(error: &warp::Error) -> bool {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(error);
+    while let Some(e) = source {
+        if let Some(io) = e.downcast_ref::<std::io::Error>()
+            && io.kind() == std::io::ErrorKind::AddrInUse
+        {
+            return true;
+        }
+        source = e.source();
+    }
+    false
+}
+
+async fn get_status(state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
+    let state = state.lock().await;
+    Ok(warp::reply::json(&serde_json::json!({
+        "status": "running",
+        "version": env!("CARGO_PKG_VERSION"),
+        "started_at": state.started_at,
+        "uptime_secs": state.uptime_secs(),
+        "paused": state.paused,
+    })))
+}
+
+// List endpoints omit build output, per step too; the dashboard loads it
+// from api/build/{id} on demand
+fn strip_build_output(builds: &mut serde_json::Value) {
+    if let Some(builds) = builds.as_array_mut() {
+        for build in builds {
+            if let Some(build) = build.as_object_mut() {
+                build.remove("output");
+                if let Some(steps) = build.get_mut("steps").and_then(|steps| steps.as_array_mut()) {
+                    for step in steps.iter_mut().filter_map(|step| step.as_object_mut()) {
+                        step.remove("output");
+                        step.remove("stdout");
+                        step.remove("stderr");
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Anyone who can reach the API could otherwise forge webhooks or read the
+// tokens passed to build commands
+fn hide_secrets(repo_state: &mut serde_json::Value) {
+    let Some(repository) = repo_state["repository"].as_object_mut() else {
+        return;
+    };
+    if repository.get("webhook_secret").is_some_and(|secret| !secret.is_null()) {
+        repository.insert("webhook_secret".to_string(), serde_json::json!(config::MASKED));
+    }
+    if let Some(env) = repository.get_mut("env").and_then(|env| env.as_object_mut()) {
+        for value in env.values_mut() {
+            *value = serde_json::json!(config::MASKED);
+        }
     }
 }
 
-async fn get_status() -> Result<impl warp::Reply, warp::Rejection> {
-    Ok(warp::reply::json(&serde_json::json!({"status": "running"})))
+// Only settings relevant to the dashboard are exposed
+async fn get_ui_config(config: Config) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&serde_json::json!({
+        "refresh_interval_secs": config.dashboard_refresh_secs,
+        "commit_hash_length": config.commit_hash_length.clamp(MIN_COMMIT_PREFIX, 40),
+    })))
 }
 
 async fn get_repositories(state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
-    let state = state.lock().unwrap();
-    let repositories: Vec<_> = state.repositories.values().collect();
+    let state = state.lock().await;
+    let mut repo_states: Vec<_> = state.repositories.values().collect();
+    repo_states.sort_by(|a, b| {
+        a.repository.order.cmp(&b.repository.order)
+            .then_with(|| a.repository.name.cmp(&b.repository.name))
+    });
+    let repositories: Vec<_> = repo_states
+        .into_iter()
+        .map(|repo_state| {
+            let mut value = serde_json::to_value(repo_state).unwrap_or_default();
+            strip_build_output(&mut value["builds"]);
+            hide_secrets(&mut value);
+            value["health_score"] = serde_json::json!(repo_state.health_score());
+            value
+        })
+        .collect();
     Ok(warp::reply::json(&repositories))
 }
 
+// Minimal payload for status walls; empty when everything is green
+async fn get_failing_repositories(state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
+    let state = state.lock().await;
+    let mut repo_states: Vec<_> = state.repositories.values().filter(|rs| rs.is_failing()).collect();
+    repo_states.sort_by(|a, b| {
+        a.repository.order.cmp(&b.repository.order)
+            .then_with(|| a.repository.name.cmp(&b.repository.name))
+    });
+    let failing: Vec<_> = repo_states.into_iter().map(|rs| rs.failing_summary()).collect();
+    Ok(warp::reply::json(&failing))
+}
+
+async fn get_projects(state: SharedGlobalState, config: Config) -> Result<impl warp::Reply, warp::Rejection> {
+    let state = state.lock().await;
+    let projects: Vec<_> = config.projects.iter().map(|project| state.project_status(project)).collect();
+    Ok(warp::reply::json(&projects))
+}
+
 async fn get_repository(repo_name: String, state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
-    let state = state.lock().unwrap();
-    if let Some((_, repo_state)) = state.repositories.iter().find(|(_, rs)| rs.repository.name == repo_name) {
-        Ok(warp::reply::json(repo_state))
-    } else {
-        Ok(warp::reply::json(&serde_json::json!({"error": "Repository not found"})))
+    let state = state.lock().await;
+    match state.repositories.values().find(|rs| rs.repository.name == repo_name) {
+        Some(repo_state) => {
+            let mut value = serde_json::to_value(repo_state).unwrap_or_default();
+            hide_secrets(&mut value);
+            Ok(warp::reply::json(&value))
+        }
+        None => Err(ApiError::not_found("Repository not found")),
     }
 }
 
-async fn get_recent_builds(state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
-    let state = state.lock().unwrap();
-    Ok(warp::reply::json(&state.recent_builds))
+async fn get_badge(repo_name: String, query: BadgeQuery, state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
+    let (message, color, status) = {
+        let state = state.lock().await;
+        match state.repositories.values().find(|rs| rs.repository.name == repo_name) {
+            Some(repo_state) => {
+                let (message, color) = match repo_state.current_status.as_str() {
+                    "Passing" => ("passing", "#4c1"),
+                    "Failed" => ("failing", "#e05d44"),
+                    "Unstable" => ("unstable", "#dfb317"),
+                    "NoCommands" => ("no commands", "#e05d44"),
+                    // Building, idle after a restart and so on: fall back to the last result
+                    _ => match repo_state.builds.first() {
+                        Some(build) if build.success => ("passing", "#4c1"),
+                        Some(_) => ("failing", "#e05d44"),
+                        None => ("unknown", "#9f9f9f"),
+                    },
+                };
+                (message, color, StatusCode::OK)
+            }
+            None => ("not found", "#9f9f9f", StatusCode::NOT_FOUND),
+        }
+    };
+    
+    let label = query.label.unwrap_or(repo_name);
+    let svg = render_badge(&label, message, color, query.style);
+    let reply = warp::reply::with_header(svg, "content-type", "image/svg+xml");
+    let reply = warp::reply::with_header(reply, "cache-control", "no-cache");
+    Ok(warp::reply::with_status(reply, status))
+}
+
+fn render_badge(label: &str, message: &str, color: &str, style: BadgeStyle) -> String {
+    let (label, message) = match style {
+        BadgeStyle::ForTheBadge => (label.to_uppercase(), message.to_uppercase()),
+        _ => (label.to_string(), message.to_string()),
+    };
+    // Approximate Verdana advance widths; shields.io measures real glyphs
+    let (char_width, padding, height) = match style {
+        BadgeStyle::Flat => (6.5, 10.0, 20),
+        BadgeStyle::Plastic => (6.5, 10.0, 18),
+        BadgeStyle::ForTheBadge => (7.5, 24.0, 28),
+    };
+    let width_of = |text: &str| (text.chars().count() as f64 * char_width + padding).round() as u32;
+    let label_width = width_of(&label);
+    let message_width = width_of(&message);
+    let width = label_width + message_width;
+    let label_x = label_width as f64 / 2.0;
+    let message_x = label_width as f64 + message_width as f64 / 2.0;
+    let label = escape_xml(&label);
+    let message = escape_xml(&message);
+    
+    let (radius, gradient, text) = match style {
+        BadgeStyle::Flat => (
+            3,
+            r##"<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#bbb" stop-opacity=".1"/><stop offset="1" stop-opacity=".1"/></linearGradient>"##,
+            format!(
+                r##"<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{lx}" y="15" fill="#010101" fill-opacity=".3">{label}</text><text x="{lx}" y="14">{label}</text><text x="{mx}" y="15" fill="#010101" fill-opacity=".3">{message}</text><text x="{mx}" y="14">{message}</text></g>"##,
+                lx = label_x, mx = message_x, label = label, message = message
+            ),
+        ),
+        BadgeStyle::Plastic => (
+            4,
+            r##"<linearGradient id="s" x2="0" y2="100%"><stop offset="0" stop-color="#fff" stop-opacity=".7"/><stop offset=".1" stop-color="#aaa" stop-opacity=".1"/><stop offset=".9" stop-opacity=".3"/><stop offset="1" stop-opacity=".5"/></linearGradient>"##,
+            format!(
+                r##"<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="11"><text x="{lx}" y="14" fill="#010101" fill-opacity=".3">{label}</text><text x="{lx}" y="13">{label}</text><text x="{mx}" y="14" fill="#010101" fill-opacity=".3">{message}</text><text x="{mx}" y="13">{message}</text></g>"##,
+                lx = label_x, mx = message_x, label = label, message = message
+            ),
+        ),
+        BadgeStyle::ForTheBadge => (
+            0,
+            "",
+            format!(
+                r##"<g fill="#fff" text-anchor="middle" font-family="Verdana,Geneva,DejaVu Sans,sans-serif" font-size="10" letter-spacing="1"><text x="{lx}" y="17.5">{label}</text><text x="{mx}" y="17.5" font-weight="bold">{message}</text></g>"##,
+                lx = label_x, mx = message_x, label = label, message = message
+            ),
+        ),
+    };
+    let shading = if gradient.is_empty() {
+        String::new()
+    } else {
+        format!(r#"<rect width="{}" height="{}" fill="url(#s)"/>"#, width, height)
+    };
+    
+    format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" role="img" aria-label="{label}: {message}"><title>{label}: {message}</title>{gradient}<clipPath id="r"><rect width="{w}" height="{h}" rx="{radius}" fill="#fff"/></clipPath><g clip-path="url(#r)"><rect width="{lw}" height="{h}" fill="#555"/><rect x="{lw}" width="{mw}" height="{h}" fill="{color}"/>{shading}</g>{text}</svg>"##,
+        w = width, h = height, lw = label_width, mw = message_width,
+        label = label, message = message, gradient = gradient, radius = radius,
+        color = color, shading = shading, text = text
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+async fn get_recent_builds(query: BuildsQuery, state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
+    let commit = query.commit.map(|commit| commit.to_lowercase());
+    if let Some(ref prefix) = commit
+        && (prefix.len() < MIN_COMMIT_PREFIX || !prefix.chars().all(|c| c.is_ascii_hexdigit()))
+    {
+        return Err(ApiError::bad_request(format!(
+            "commit must be a hex hash or prefix of at least {} characters", MIN_COMMIT_PREFIX
+        )));
+    }
+    
+    let state = state.lock().await;
+    let mut builds = serde_json::to_value(state.list_builds(commit.as_deref(), query.limit, query.offset)).unwrap_or_default();
+    strip_build_output(&mut builds);
+    Ok(warp::reply::json(&builds))
 }
 
 async fn get_build_detail(id: u64, state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
-    let state = state.lock().unwrap();
-    if let Some(build) = state.recent_builds.iter().find(|b| b.id == id) {
-        Ok(warp::reply::json(build))
+    let state = state.lock().await;
+    match state.find_build(id) {
+        Some(build) => Ok(warp::reply::json(&build)),
+        None => Err(ApiError::not_found("Build not found")),
+    }
+}
+
+// Plain-text output for piping into other tools; redaction was applied when the build ran
+async fn get_build_output(id: u64, state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
+    let state = state.lock().await;
+    let Some(build) = state.find_build(id) else {
+        return Err(ApiError::not_found("Build not found"));
+    };
+    Ok(warp::reply::with_header(build.output, "content-type", "text/plain; charset=utf-8"))
+}
+
+// The output as a file attachment, headed by what was built, for bug reports
+async fn download_build_log(id: u64, state: SharedGlobalState) -> Result<warp::reply::Response, warp::Rejection> {
+    let build = state.lock().await.find_build(id);
+    let Some(build) = build else {
+        return Err(ApiError::not_found("Build not found"));
+    };
+    
+    let name = match &build.subproject {
+        Some(subproject) => format!("{}-{}", build.repository_name, subproject),
+        None => build.repository_name.clone(),
+    };
+    let safe_name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.' { c } else { '_' })
+        .collect();
+    let mut body = format!(
+        "Repository: {}\nBuild: #{} ({} build #{})\nCommit: {}\nResult: {}\nDuration: {} ms\n\n",
+        name,
+        build.id,
+        build.repository_name,
+        build.repo_build_number,
+        build.commit_hash,
+        if build.success { "success" } else { "failure" },
+        build.duration_ms
+    );
+    body.push_str(&build.output);
+    
+    let reply = warp::reply::with_header(body, "content-type", "text/plain; charset=utf-8");
+    let disposition = format!("attachment; filename=\"{}-build-{}.log\"", safe_name, build.id);
+    Ok(warp::reply::with_header(reply, "content-disposition", disposition).into_response())
+}
+
+const MAX_NOTE_BYTES: u64 = 4096;
+
+// An empty body clears the note
+async fn set_build_note(id: u64, body: warp::hyper::body::Bytes, state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
+    let note = String::from_utf8_lossy(&body).trim().to_string();
+    let note = if note.is_empty() { None } else { Some(note) };
+    
+    let mut state = state.lock().await;
+    if state.set_build_note(id, note.clone()) {
+        Ok(warp::reply::json(&serde_json::json!({"id": id, "note": note})))
+    } else {
+        Err(ApiError::not_found("Build not found"))
+    }
+}
+
+async fn compare_builds(query: CompareQuery, state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
+    let state = state.lock().await;
+    match (state.find_build(query.a), state.find_build(query.b)) {
+        (Some(a), Some(b)) => Ok(warp::reply::json(&BuildComparison::new(a, b))),
+        _ => Err(ApiError::not_found("Build not found")),
+    }
+}
+
+async fn trigger_repository(repo_name: String, state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut state = state.lock().await;
+    let repo_id = state.repositories
+        .iter()
+        .find(|(_, rs)| rs.repository.name == repo_name)
+        .map(|(id, _)| *id);
+    
+    match repo_id {
+        Some(id) if state.request_build(&id) => {
+            Ok(warp::reply::json(&serde_json::json!({"queued": 1})))
+        }
+        Some(_) => Err(ApiError::conflict("Repository is disabled")),
+        None => Err(ApiError::not_found("Repository not found")),
+    }
+}
+
+async fn acknowledge_repository(repo_name: String, state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut state = state.lock().await;
+    let repo_id = state.repositories
+        .iter()
+        .find(|(_, rs)| rs.repository.name == repo_name)
+        .map(|(id, _)| *id);
+    
+    match repo_id {
+        Some(id) if state.acknowledge(&id) => Ok(warp::reply::json(&serde_json::json!({"acknowledged": true}))),
+        Some(_) => Err(ApiError::conflict("Repository is not failing")),
+        None => Err(ApiError::not_found("Repository not found")),
+    }
+}
+
+async fn rerun_build(id: u64, state: SharedGlobalState, config: Config) -> Result<impl warp::Reply, warp::Rejection> {
+    let build = {
+        let state = state.lock().await;
+        state.find_build(id)
+    };
+    let Some(build) = build else {
+        return Err(ApiError::not_found("Build not found"));
+    };
+    
+    if !ci_runner::commit_exists(config.git(), &build.repo_path, &build.commit_hash).await {
+        return Err(ApiError::conflict(format!(
+            "Commit {} no longer exists in {}", build.commit_hash, build.repository_name
+        )));
+    }
+    
+    let mut state = state.lock().await;
+    if state.request_rerun(&build) {
+        Ok(warp::reply::json(&serde_json::json!({"queued": 1, "rerun_of": id})))
     } else {
-        Ok(warp::reply::json(&serde_json::json!({"error": "Build not found"})))
+        Err(ApiError::conflict("Repository is disabled"))
+    }
+}
+
+// Push payloads can list many commits, but they're never needed beyond the signature
+const MAX_WEBHOOK_BYTES: u64 = 1024 * 1024;
+
+async fn receive_webhook(
+    repo_name: String,
+    headers: warp::http::HeaderMap,
+    body: warp::hyper::body::Bytes,
+    state: SharedGlobalState,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (repo_id, secret) = {
+        let state = state.lock().await;
+        match state.repositories.iter().find(|(_, rs)| rs.repository.name == repo_name) {
+            Some((id, repo_state)) => (*id, repo_state.repository.webhook_secret.clone()),
+            None => return Err(ApiError::not_found("Repository not found")),
+        }
+    };
+    let Some(secret) = secret else {
+        return Err(ApiError::forbidden("Webhooks are not enabled for this repository; set its webhook_secret"));
+    };
+    let Some(provider) = Provider::detect(&headers) else {
+        return Err(ApiError::bad_request("Unrecognized webhook; expected GitHub or GitLab headers"));
+    };
+    if !webhook::verify_signature(provider, &secret, &headers, &body) {
+        logging::warn(Some(&repo_name), "webhook_rejected", &format!("Rejected {:?} webhook with an invalid signature", provider));
+        return Err(ApiError::unauthorized("Invalid webhook signature"));
+    }
+    
+    if state.lock().await.notify_webhook(&repo_id) {
+        Ok(warp::reply::json(&serde_json::json!({"accepted": true})))
+    } else {
+        Err(ApiError::conflict("Repository is disabled"))
+    }
+}
+
+async fn trigger_all(state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut state = state.lock().await;
+    let queued = state.request_all_builds();
+    Ok(warp::reply::json(&serde_json::json!({"queued": queued})))
+}
+
+async fn get_events(query: EventsQuery, state: SharedGlobalState) -> Result<impl warp::Reply, warp::Rejection> {
+    let state = state.lock().await;
+    Ok(warp::reply::json(&state.events.recent(query.repository.as_deref(), query.limit)))
+}
+
+async fn set_paused(state: SharedGlobalState, paused: bool) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut state = state.lock().await;
+    if state.paused != paused {
+        let message = if paused { "⏸️  Builds paused" } else { "▶️  Builds resumed" };
+        logging::info(None, if paused { "daemon_paused" } else { "daemon_resumed" }, message);
     }
+    state.set_paused(paused);
+    Ok(warp::reply::json(&serde_json::json!({"paused": paused})))
 }
 
 async fn serve_index() -> Result<impl warp::Reply, warp::Rejection> {
@@ -104,84 +730,165 @@ const HTML_TEMPLATE: &str = r#"
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
     <title>Turbulent CI Multi-Repository Dashboard</title>
+    <script>
+        // Apply the saved theme before first paint to avoid a flash
+        document.documentElement.setAttribute('data-theme', localStorage.getItem('turbulent-theme') || 'light');
+    </script>
     <style>
+        :root {
+            --bg: #f8fafc;
+            --surface: white;
+            --border: #e2e8f0;
+            --border-subtle: #f1f5f9;
+            --text: #1e293b;
+            --text-secondary: #475569;
+            --text-muted: #64748b;
+            --text-faint: #94a3b8;
+        }
+        [data-theme="dark"] {
+            --bg: #0b1220;
+            --surface: #111827;
+            --border: #334155;
+            --border-subtle: #1f2937;
+            --text: #e5e7eb;
+            --text-secondary: #cbd5e1;
+            --text-muted: #94a3b8;
+            --text-faint: #64748b;
+        }
+        [data-theme="dark"] .status.passing { background: rgba(34, 197, 94, 0.2); color: #86efac; }
+        [data-theme="dark"] .status.failed, [data-theme="dark"] .status.error, [data-theme="dark"] .status.autherror, [data-theme="dark"] .status.nocommands { background: rgba(239, 68, 68, 0.2); color: #fca5a5; }
+        [data-theme="dark"] .status.building { background: rgba(234, 179, 8, 0.2); color: #fde68a; }
+        [data-theme="dark"] .status.unstable { background: rgba(249, 115, 22, 0.2); color: #fdba74; }
+        [data-theme="dark"] .status.missing { background: rgba(99, 102, 241, 0.2); color: #c7d2fe; }
+        [data-theme="dark"] .status.paused, [data-theme="dark"] .status.superseded, [data-theme="dark"] .status.interrupted { background: rgba(148, 163, 184, 0.2); color: #cbd5e1; }
+        [data-theme="dark"] .output { border: 1px solid var(--border); }
+        [data-theme="dark"] .output-search { background: var(--bg); color: var(--text); }
+        [data-theme="dark"] .stat-card, [data-theme="dark"] .repo-card { box-shadow: none; }
         * { margin: 0; padding: 0; box-sizing: border-box; }
-        body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', system-ui, sans-serif; background: #f8fafc; }
+        body { font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', system-ui, sans-serif; background: var(--bg); }
         .container { max-width: 1400px; margin: 0 auto; padding: 20px; }
-        .header { background: white; padding: 24px; border-radius: 12px; margin-bottom: 24px; box-shadow: 0 4px 6px rgba(0,0,0,0.07); border: 1px solid #e2e8f0; }
-        .header h1 { font-size: 32px; margin-bottom: 8px; color: #1e293b; }
-        .header .subtitle { color: #64748b; font-size: 16px; }
+        .header { background: var(--surface); padding: 24px; border-radius: 12px; margin-bottom: 24px; box-shadow: 0 4px 6px rgba(0,0,0,0.07); border: 1px solid var(--border); }
+        .header h1 { font-size: 32px; margin-bottom: 8px; color: var(--text); }
+        .header { position: relative; }
+        .theme-toggle { position: absolute; top: 24px; right: 24px; background: var(--border-subtle); color: var(--text); border: 1px solid var(--border); border-radius: 8px; padding: 8px 12px; cursor: pointer; font-size: 16px; }
+        .header .subtitle { color: var(--text-muted); font-size: 16px; }
 
         .nav-tabs { display: flex; gap: 8px; margin-bottom: 24px; }
-        .nav-tab { background: white; border: 1px solid #e2e8f0; padding: 12px 20px; border-radius: 8px; cursor: pointer; transition: all 0.2s; color: #64748b; font-weight: 500; }
+        .nav-tab { background: var(--surface); border: 1px solid var(--border); padding: 12px 20px; border-radius: 8px; cursor: pointer; transition: all 0.2s; color: var(--text-muted); font-weight: 500; }
         .nav-tab.active { background: #3b82f6; color: white; border-color: #3b82f6; }
-        .nav-tab:hover:not(.active) { background: #f8fafc; }
+        .nav-tab:hover:not(.active) { background: var(--bg); }
+        .nav-tab:focus-visible { outline: 2px solid #3b82f6; outline-offset: 2px; }
 
         .tab-content { display: none; }
         .tab-content.active { display: block; }
 
         .repo-grid { display: grid; grid-template-columns: repeat(auto-fit, minmax(350px, 1fr)); gap: 20px; margin-bottom: 24px; }
-        .repo-card { background: white; border-radius: 12px; padding: 20px; box-shadow: 0 4px 6px rgba(0,0,0,0.07); border: 1px solid #e2e8f0; }
+        .repo-card { background: var(--surface); border-radius: 12px; padding: 20px; box-shadow: 0 4px 6px rgba(0,0,0,0.07); border: 1px solid var(--border); }
         .repo-header { display: flex; justify-content: between; align-items: center; margin-bottom: 16px; }
-        .repo-name { font-size: 18px; font-weight: 600; color: #1e293b; }
+        .repo-name { font-size: 18px; font-weight: 600; color: var(--text); }
         .repo-status { display: flex; align-items: center; gap: 8px; }
 
         .status { display: inline-block; padding: 6px 12px; border-radius: 20px; font-size: 11px; font-weight: 600; text-transform: uppercase; letter-spacing: 0.5px; }
         .status.passing { background: #dcfce7; color: #166534; }
         .status.failed { background: #fecaca; color: #991b1b; }
         .status.building { background: #fef3c7; color: #92400e; }
-        .status.idle { background: #e2e8f0; color: #475569; }
-        .status.error { background: #fee2e2; color: #991b1b; }
-
+        .status.idle { background: var(--border); color: var(--text-secondary); }
+        .status.error, .status.autherror, .status.nocommands { background: #fee2e2; color: #991b1b; }
+        .status.unstable { background: #ffedd5; color: #9a3412; }
+        .status.missing { background: #e0e7ff; color: #3730a3; }
+        .status.paused, .status.superseded, .status.interrupted { background: #f1f5f9; color: #475569; }
+
+        .trigger-badge { background: #ede9fe; color: #5b21b6; padding: 4px 8px; border-radius: 12px; font-size: 11px; font-weight: 600; }
+        .ack-badge { background: #e0f2fe; color: #075985; padding: 4px 8px; border-radius: 12px; font-size: 10px; font-weight: 600; }
+        .repo-card.acknowledged { opacity: 0.6; }
+        .upstream-badge { background: var(--border); color: var(--text-secondary); padding: 4px 8px; border-radius: 12px; font-size: 10px; font-weight: 600; }
+        .dirty-badge { background: #ffedd5; color: #9a3412; padding: 4px 8px; border-radius: 12px; font-size: 10px; font-weight: 600; }
         .project-type { background: #e0e7ff; color: #3730a3; padding: 4px 8px; border-radius: 12px; font-size: 10px; font-weight: 600; }
+        .project-type.rust { background: #ffedd5; color: #9a3412; }
+        .project-type.python { background: #dbeafe; color: #1e40af; }
+        .project-type.node { background: #dcfce7; color: #166534; }
+        .project-type.deno { background: #e5e7eb; color: #111827; }
+        .project-type.bun { background: #fef3c7; color: #78350f; }
 
         .repo-info { display: grid; grid-template-columns: 1fr 1fr; gap: 16px; margin-bottom: 16px; }
         .repo-detail { }
-        .repo-detail strong { display: block; color: #475569; font-size: 12px; text-transform: uppercase; letter-spacing: 0.5px; margin-bottom: 4px; }
-        .repo-detail div { color: #1e293b; font-weight: 500; }
+        .repo-detail strong { display: block; color: var(--text-secondary); font-size: 12px; text-transform: uppercase; letter-spacing: 0.5px; margin-bottom: 4px; }
+        .repo-detail div { color: var(--text); font-weight: 500; }
 
         .recent-builds { margin-top: 16px; }
-        .recent-builds h4 { color: #475569; font-size: 14px; margin-bottom: 12px; }
-        .build-item-small { display: flex; align-items: center; gap: 8px; padding: 8px 0; border-bottom: 1px solid #f1f5f9; }
+        .recent-builds h4 { color: var(--text-secondary); font-size: 14px; margin-bottom: 12px; }
+        .build-item-small { display: flex; align-items: center; gap: 8px; padding: 8px 0; border-bottom: 1px solid var(--border-subtle); }
         .build-item-small:last-child { border-bottom: none; }
+        .slow-build { color: #c2410c; font-weight: 600; }
+        .build-progress { height: 6px; background: var(--border-subtle); border-radius: 3px; overflow: hidden; margin: 8px 0 4px; }
+        .build-progress-bar { height: 100%; background: #eab308; transition: width 0.3s; }
         .build-icon { font-size: 14px; }
+        .overview-sort { align-items: center; margin-bottom: 16px; }
+        .step-section { border-bottom: 1px solid var(--border-subtle); }
+        .step-section:last-child { border-bottom: none; }
+        .step-section > summary { cursor: pointer; list-style: none; border-bottom: none; }
+        .step-section > summary::-webkit-details-marker { display: none; }
+        .step-section > summary::before { content: '▸'; color: var(--text-muted); font-size: 12px; }
+        .step-section[open] > summary::before { content: '▾'; }
+        .step-output { max-height: 40vh; margin-bottom: 12px; padding: 12px; font-size: 12px; }
+        .environment-list { margin-bottom: 24px; font-size: 13px; }
+        .environment-item { display: flex; gap: 16px; padding: 6px 0; border-bottom: 1px solid var(--border-subtle); color: var(--text-secondary); }
+        .environment-item code { min-width: 200px; color: var(--text-muted); }
+        .step-stream { font-size: 11px; font-weight: 600; color: var(--text-muted); text-transform: uppercase; margin: 4px 0; }
         .build-info-small { flex: 1; }
-        .build-id { font-weight: 600; color: #1e293b; font-size: 13px; }
-        .build-time { font-size: 11px; color: #64748b; }
+        .build-id { font-weight: 600; color: var(--text); font-size: 13px; }
+        .build-time { font-size: 11px; color: var(--text-muted); }
 
-        .builds-section { background: white; border-radius: 12px; box-shadow: 0 4px 6px rgba(0,0,0,0.07); border: 1px solid #e2e8f0; }
-        .builds-header { padding: 20px; border-bottom: 1px solid #f1f5f9; display: flex; justify-content: between; align-items: center; }
-        .builds-header h2 { color: #1e293b; }
+        .builds-section { background: var(--surface); border-radius: 12px; box-shadow: 0 4px 6px rgba(0,0,0,0.07); border: 1px solid var(--border); }
+        .builds-header { padding: 20px; border-bottom: 1px solid var(--border-subtle); display: flex; justify-content: between; align-items: center; }
+        .builds-header h2 { color: var(--text); }
         .filter-buttons { display: flex; gap: 8px; }
-        .filter-btn { padding: 6px 12px; border: 1px solid #e2e8f0; background: white; border-radius: 6px; font-size: 12px; cursor: pointer; }
+        .filter-btn { padding: 6px 12px; border: 1px solid var(--border); background: var(--surface); border-radius: 6px; font-size: 12px; cursor: pointer; }
         .filter-btn.active { background: #3b82f6; color: white; border-color: #3b82f6; }
 
-        .build-item { padding: 20px; border-bottom: 1px solid #f1f5f9; display: flex; align-items: center; justify-content: space-between; transition: background-color 0.2s; }
-        .build-item:hover { background: #f8fafc; }
+        .event-item { padding: 12px 20px; border-bottom: 1px solid var(--border-subtle); display: flex; align-items: center; gap: 12px; font-size: 14px; color: var(--text-secondary); }
+        .event-item .build-time { min-width: 90px; }
+        .build-item { padding: 20px; border-bottom: 1px solid var(--border-subtle); display: flex; align-items: center; justify-content: space-between; transition: background-color 0.2s; }
+        .build-item:hover { background: var(--bg); }
+        .compare-checkbox { margin-right: 16px; width: 16px; height: 16px; cursor: pointer; }
+        .btn:disabled { opacity: 0.5; cursor: not-allowed; }
         .build-item:last-child { border-bottom: none; }
         .build-info { flex: 1; }
         .build-header { display: flex; align-items: center; gap: 12px; margin-bottom: 8px; }
-        .build-meta { font-size: 13px; color: #64748b; display: flex; gap: 16px; flex-wrap: wrap; }
+        .build-meta { font-size: 13px; color: var(--text-muted); display: flex; gap: 16px; flex-wrap: wrap; }
         .build-actions { display: flex; gap: 8px; }
         .btn { padding: 8px 16px; border: none; border-radius: 8px; cursor: pointer; font-size: 12px; font-weight: 500; transition: all 0.2s; }
         .btn-primary { background: #3b82f6; color: white; }
         .btn-primary:hover { background: #2563eb; }
-        .btn-secondary { background: #f1f5f9; color: #475569; }
-        .btn-secondary:hover { background: #e2e8f0; }
+        .btn-secondary { background: var(--border-subtle); color: var(--text-secondary); }
+        .btn-secondary:hover { background: var(--border); }
+        a.btn { text-decoration: none; }
 
         .modal { display: none; position: fixed; top: 0; left: 0; right: 0; bottom: 0; background: rgba(0,0,0,0.5); z-index: 1000; }
-        .modal-content { background: white; margin: 2% auto; padding: 24px; width: 95%; max-width: 900px; border-radius: 12px; max-height: 90vh; overflow-y: auto; }
-        .output { background: #0f172a; color: #e2e8f0; padding: 20px; border-radius: 8px; font-family: 'SF Mono', Monaco, 'Cascadia Code', monospace; font-size: 13px; white-space: pre-wrap; line-height: 1.5; }
+        .modal-content { background: var(--surface); margin: 2% auto; padding: 24px; width: 95%; max-width: 900px; border-radius: 12px; max-height: 90vh; overflow-y: auto; }
+        .output-toolbar { display: flex; gap: 8px; align-items: center; margin-bottom: 12px; }
+        .output-search { flex: 1; padding: 8px 12px; border: 1px solid var(--border); border-radius: 8px; font-size: 13px; }
+        .output-matches { font-size: 12px; color: var(--text-muted); min-width: 80px; text-align: right; }
+        .output mark { background: #facc15; color: #0f172a; border-radius: 2px; }
+        .output-line.error-line { background: rgba(220, 38, 38, 0.35); }
+        .output { max-height: 60vh; overflow-y: auto; background: #0f172a; color: #e2e8f0; padding: 20px; border-radius: 8px; font-family: 'SF Mono', Monaco, 'Cascadia Code', monospace; font-size: 13px; white-space: pre-wrap; line-height: 1.5; }
         .refresh-btn { position: fixed; bottom: 24px; right: 24px; background: #3b82f6; color: white; border: none; padding: 16px; border-radius: 50%; cursor: pointer; box-shadow: 0 8px 25px rgba(59, 130, 246, 0.3); font-size: 18px; }
-        .empty-state { padding: 60px 20px; text-align: center; color: #64748b; }
-        .loading { padding: 40px; text-align: center; color: #94a3b8; }
-
-        .repo-path { font-family: 'SF Mono', Monaco, monospace; font-size: 12px; background: #f8fafc; padding: 4px 8px; border-radius: 4px; color: #475569; }
+        .pause-btn { position: fixed; bottom: 24px; right: 92px; background: var(--surface); color: var(--text-secondary); border: 1px solid var(--border); padding: 16px; border-radius: 50%; cursor: pointer; box-shadow: 0 8px 25px rgba(0,0,0,0.08); font-size: 18px; }
+        .paused-banner { display: flex; align-items: center; justify-content: space-between; gap: 16px; background: #fef3c7; color: #92400e; border: 1px solid #fcd34d; border-radius: 12px; padding: 16px 20px; margin-bottom: 24px; font-weight: 600; }
+        .paused-banner[hidden] { display: none; }
+        [data-theme="dark"] .paused-banner { background: rgba(245, 158, 11, 0.15); color: #fcd34d; border-color: rgba(245, 158, 11, 0.4); }
+        .pause-btn.paused { background: #fef3c7; border-color: #fcd34d; }
+        .repo-search { display: flex; gap: 8px; margin-bottom: 16px; }
+        .repo-search input { flex: 1; padding: 10px 14px; border: 1px solid var(--border); border-radius: 8px; font-size: 14px; background: var(--surface); color: var(--text); }
+        .empty-state { padding: 60px 20px; text-align: center; color: var(--text-muted); }
+        .loading { padding: 40px; text-align: center; color: var(--text-faint); }
+
+        .repo-path { font-family: 'SF Mono', Monaco, monospace; font-size: 12px; background: var(--bg); padding: 4px 8px; border-radius: 4px; color: var(--text-secondary); }
 
         .summary-stats { display: grid; grid-template-columns: repeat(auto-fit, minmax(150px, 1fr)); gap: 16px; margin-bottom: 24px; }
-        .stat-card { background: white; padding: 16px; border-radius: 8px; border: 1px solid #e2e8f0; text-align: center; }
-        .stat-number { font-size: 24px; font-weight: 700; color: #1e293b; }
-        .stat-label { font-size: 12px; color: #64748b; text-transform: uppercase; letter-spacing: 0.5px; margin-top: 4px; }
+        .stat-card { background: var(--surface); padding: 16px; border-radius: 8px; border: 1px solid var(--border); text-align: center; }
+        .stat-number { font-size: 24px; font-weight: 700; color: var(--text); }
+        .stat-label { font-size: 12px; color: var(--text-muted); text-transform: uppercase; letter-spacing: 0.5px; margin-top: 4px; }
     </style>
 </head>
 <body>
@@ -189,30 +896,54 @@ const HTML_TEMPLATE: &str = r#"
         <div class="header">
             <h1>🌪️ Turbulent CI Dashboard</h1>
             <div class="subtitle">Multi-Repository Continuous Integration</div>
+            <button class="theme-toggle" id="theme-toggle" onclick="toggleTheme()" title="Toggle dark mode">🌙</button>
+        </div>
+
+        <div class="paused-banner" id="paused-banner" role="alert" hidden>
+            <span>⏸️ Builds are paused. New commits are tracked and will be built once resumed.</span>
+            <button class="btn btn-primary" onclick="resumeBuilds()">Resume</button>
+        </div>
+
+        <div class="nav-tabs" role="tablist" aria-label="Dashboard sections">
+            <div class="nav-tab active" role="tab" tabindex="0" aria-selected="true" aria-controls="overview-tab" aria-keyshortcuts="1" data-tab="overview" onclick="switchTab('overview')">📊 Overview</div>
+            <div class="nav-tab" role="tab" tabindex="-1" aria-selected="false" aria-controls="projects-tab" aria-keyshortcuts="2" data-tab="projects" onclick="switchTab('projects')">🧩 Projects</div>
+            <div class="nav-tab" role="tab" tabindex="-1" aria-selected="false" aria-controls="repositories-tab" aria-keyshortcuts="3" data-tab="repositories" onclick="switchTab('repositories')">📁 Repositories</div>
+            <div class="nav-tab" role="tab" tabindex="-1" aria-selected="false" aria-controls="builds-tab" aria-keyshortcuts="4" data-tab="builds" onclick="switchTab('builds')">🔨 Recent Builds</div>
+            <div class="nav-tab" role="tab" tabindex="-1" aria-selected="false" aria-controls="events-tab" aria-keyshortcuts="5" data-tab="events" onclick="switchTab('events')">📜 Events</div>
         </div>
 
-        <div class="nav-tabs">
-            <div class="nav-tab active" onclick="switchTab('overview')">📊 Overview</div>
-            <div class="nav-tab" onclick="switchTab('repositories')">📁 Repositories</div>
-            <div class="nav-tab" onclick="switchTab('builds')">🔨 Recent Builds</div>
+        <div class="repo-search" id="repo-search">
+            <input type="search" id="repo-search-input" placeholder="Filter repositories by name, path or type..." oninput="setRepoQuery(this.value)">
+            <button class="btn btn-secondary" onclick="clearRepoQuery()" title="Clear filter">✕</button>
         </div>
 
-        <div id="overview-tab" class="tab-content active">
+        <div id="overview-tab" class="tab-content active" role="tabpanel">
             <div class="summary-stats" id="summary-stats">
                 <div class="loading">Loading statistics...</div>
             </div>
+            <div class="filter-buttons overview-sort">
+                <span class="build-time">Sort by</span>
+                <button class="filter-btn" data-sort="health" onclick="setOverviewSort('health')" title="Least healthy first">Health</button>
+                <button class="filter-btn" data-sort="name" onclick="setOverviewSort('name')">Name</button>
+            </div>
             <div class="repo-grid" id="repo-overview">
                 <div class="loading">Loading repositories...</div>
             </div>
         </div>
 
-        <div id="repositories-tab" class="tab-content">
+        <div id="projects-tab" class="tab-content" role="tabpanel">
+            <div class="repo-grid" id="projects-container">
+                <div class="loading">Loading projects...</div>
+            </div>
+        </div>
+
+        <div id="repositories-tab" class="tab-content" role="tabpanel">
             <div class="repo-grid" id="repositories-container">
                 <div class="loading">Loading repositories...</div>
             </div>
         </div>
 
-        <div id="builds-tab" class="tab-content">
+        <div id="builds-tab" class="tab-content" role="tabpanel">
             <div class="builds-section">
                 <div class="builds-header">
                     <h2>Recent Builds</h2>
@@ -220,6 +951,7 @@ const HTML_TEMPLATE: &str = r#"
                         <button class="filter-btn active" onclick="filterBuilds('all')">All</button>
                         <button class="filter-btn" onclick="filterBuilds('passing')">Passing</button>
                         <button class="filter-btn" onclick="filterBuilds('failed')">Failed</button>
+                        <button class="btn btn-primary" id="compare-btn" onclick="compareSelectedBuilds()" disabled>Compare (0/2)</button>
                     </div>
                 </div>
                 <div id="builds-container">
@@ -228,14 +960,26 @@ const HTML_TEMPLATE: &str = r#"
             </div>
         </div>
 
-        <button class="refresh-btn" onclick="loadAllData()" title="Refresh">🔄</button>
+        <div id="events-tab" class="tab-content" role="tabpanel">
+            <div class="builds-section">
+                <div class="builds-header">
+                    <h2>Event Log</h2>
+                </div>
+                <div id="events-container">
+                    <div class="loading">Loading events...</div>
+                </div>
+            </div>
+        </div>
+
+        <button class="pause-btn" id="pause-btn" onclick="toggleAutoRefresh()" title="Pause auto-refresh">⏸️</button>
+        <button class="refresh-btn" onclick="loadAllData()" title="Refresh (r)" aria-label="Refresh" aria-keyshortcuts="r">🔄</button>
     </div>
 
     <div id="build-modal" class="modal">
-        <div class="modal-content">
+        <div class="modal-content" role="dialog" aria-modal="true" aria-labelledby="build-modal-title">
             <div style="display: flex; justify-content: space-between; align-items: center; margin-bottom: 24px;">
-                <h2 style="color: #1e293b;">Build Details</h2>
-                <button onclick="closeModal()" style="background: none; border: none; font-size: 24px; cursor: pointer; color: #64748b;">&times;</button>
+                <h2 id="build-modal-title" style="color: var(--text);">Build Details</h2>
+                <button onclick="closeModal()" aria-label="Close" aria-keyshortcuts="Escape" style="background: none; border: none; font-size: 24px; cursor: pointer; color: var(--text-muted);">&times;</button>
             </div>
             <div id="build-details"></div>
         </div>
@@ -243,21 +987,38 @@ const HTML_TEMPLATE: &str = r#"
 
     <script>
         let repositories = [];
+        let repoQuery = '';
         let recentBuilds = [];
+        let projects = [];
+        let events = [];
         let currentFilter = 'all';
+        let currentOutput = '';
+        let selectedBuilds = [];
+
+        // Parses a JSON response, throwing the API's error message for anything but 2xx
+        async function fetchJson(url, options) {
+            const response = await fetch(url, options);
+            const body = await response.json().catch(() => null);
+            if (!response.ok) {
+                throw new Error(body && body.error ? body.error : `${response.status} ${response.statusText}`);
+            }
+            return body;
+        }
 
         async function loadAllData() {
             await Promise.all([
                 loadRepositories(),
-                loadRecentBuilds()
+                loadRecentBuilds(),
+                loadProjects(),
+                loadEvents(),
+                loadDaemonStatus()
             ]);
             renderCurrentTab();
         }
 
         async function loadRepositories() {
             try {
-                const response = await fetch('/api/repositories');
-                repositories = await response.json();
+                repositories = await fetchJson('/api/repositories');
             } catch (error) {
                 console.error('Failed to load repositories:', error);
                 repositories = [];
@@ -266,48 +1027,235 @@ const HTML_TEMPLATE: &str = r#"
 
         async function loadRecentBuilds() {
             try {
-                const response = await fetch('/api/builds');
-                recentBuilds = await response.json();
+                recentBuilds = await fetchJson('/api/builds');
             } catch (error) {
                 console.error('Failed to load builds:', error);
                 recentBuilds = [];
             }
         }
 
+        async function loadDaemonStatus() {
+            try {
+                const status = await fetchJson('/api/status');
+                document.getElementById('paused-banner').hidden = !status.paused;
+            } catch (error) {
+                console.error('Failed to load daemon status:', error);
+            }
+        }
+
+        async function resumeBuilds() {
+            try {
+                await fetchJson('/api/resume', { method: 'POST' });
+            } catch (error) {
+                alert(`Failed to resume builds: ${error.message}`);
+            }
+            loadAllData();
+        }
+
+        async function loadProjects() {
+            try {
+                projects = await fetchJson('/api/projects');
+            } catch (error) {
+                console.error('Failed to load projects:', error);
+                projects = [];
+            }
+        }
+
+        async function loadEvents() {
+            try {
+                events = await fetchJson('/api/events/log');
+            } catch (error) {
+                console.error('Failed to load events:', error);
+                events = [];
+            }
+        }
+
         function switchTab(tabName) {
             // Update nav tabs
-            document.querySelectorAll('.nav-tab').forEach(tab => tab.classList.remove('active'));
-            event.target.classList.add('active');
+            document.querySelectorAll('.nav-tab').forEach(tab => {
+                const selected = tab.dataset.tab === tabName;
+                tab.classList.toggle('active', selected);
+                tab.setAttribute('aria-selected', selected);
+                tab.tabIndex = selected ? 0 : -1;
+            });
 
             // Update tab content
             document.querySelectorAll('.tab-content').forEach(content => content.classList.remove('active'));
             document.getElementById(tabName + '-tab').classList.add('active');
+            document.getElementById('repo-search').style.display = ['builds', 'projects', 'events'].includes(tabName) ? 'none' : '';
+
+            renderCurrentTab();
+        }
+
+        // Case-insensitive match on name, path or project type
+        function visibleRepositories() {
+            const query = repoQuery.trim().toLowerCase();
+            if (!query) return repositories;
+            return repositories.filter(repo => [repo.repository.name, repo.repository.path, repo.repository.project_type]
+                .some(value => value.toLowerCase().includes(query)));
+        }
 
+        function setRepoQuery(value) {
+            repoQuery = value;
             renderCurrentTab();
         }
 
+        function clearRepoQuery() {
+            document.getElementById('repo-search-input').value = '';
+            setRepoQuery('');
+        }
+
+        function noMatchingRepositories() {
+            return `<div class="empty-state">🔍 No repositories match "${escapeHtml(repoQuery)}"</div>`;
+        }
+
         function renderCurrentTab() {
             const activeTab = document.querySelector('.tab-content.active');
             if (activeTab.id === 'overview-tab') {
                 renderOverview();
+            } else if (activeTab.id === 'projects-tab') {
+                renderProjects();
             } else if (activeTab.id === 'repositories-tab') {
                 renderRepositories();
             } else if (activeTab.id === 'builds-tab') {
                 renderBuilds();
+            } else if (activeTab.id === 'events-tab') {
+                renderEvents();
             }
         }
 
+        function renderProjects() {
+            const container = document.getElementById('projects-container');
+            if (projects.length === 0) {
+                container.innerHTML = '<div class="empty-state">🧩 No projects configured. Group repositories with <code>projects</code> in settings.json.</div>';
+                return;
+            }
+
+            container.innerHTML = projects.map(project => `
+                <div class="repo-card">
+                    <div class="repo-header">
+                        <div class="repo-name">${escapeHtml(project.name)}</div>
+                        <div class="repo-status">
+                            <span role="status" class="status ${project.status.toLowerCase()}">${project.status}</span>
+                        </div>
+                    </div>
+                    <div class="repo-info">
+                        <div class="repo-detail">
+                            <strong>Repositories</strong>
+                            <div>${project.repositories.length}</div>
+                        </div>
+                        <div class="repo-detail">
+                            <strong>Failing</strong>
+                            <div>${project.failing}</div>
+                        </div>
+                        <div class="repo-detail">
+                            <strong>Building</strong>
+                            <div>${project.building}</div>
+                        </div>
+                        <div class="repo-detail">
+                            <strong>Success Rate</strong>
+                            <div>${project.success_rate == null ? 'n/a' : project.success_rate + '%'} of ${project.total_builds} builds</div>
+                        </div>
+                    </div>
+                    <div style="margin-top: 16px;">
+                        ${project.repositories.map(member => `
+                            <div class="build-item-small">
+                                <div class="build-info-small">
+                                    <div class="build-id">${escapeHtml(member.name)}</div>
+                                </div>
+                                <span role="status" class="status ${member.is_building ? 'building' : member.status.toLowerCase().split(':')[0]}">${member.is_building ? 'Building' : member.status.split(':')[0]}</span>
+                            </div>
+                        `).join('')}
+                    </div>
+                </div>
+            `).join('');
+        }
+
         function renderOverview() {
             renderSummaryStats();
             renderRepositoryOverview();
         }
 
+        function buildIcon(build) {
+            if (build.superseded) return '⏭️';
+            if (build.interrupted) return '⚠️';
+            return build.success ? '✅' : '❌';
+        }
+
+        function buildStatus(build) {
+            if (build.superseded) return ['superseded', 'Superseded'];
+            if (build.interrupted) return ['interrupted', 'Interrupted'];
+            return build.success ? ['passing', 'Passed'] : ['failed', 'Failed'];
+        }
+
+        function progressBar(repo) {
+            if (!repo.is_building || repo.progress == null) return '';
+            const percent = Math.round(repo.progress * 100);
+            return `
+                <div class="build-progress">
+                    <div class="build-progress-bar" role="progressbar" aria-valuenow="${percent}" aria-valuemin="0" aria-valuemax="100" style="width: ${percent}%"></div>
+                </div>
+                <div class="build-time">${repo.steps_completed} of ${repo.steps_total} steps done · ${percent}%</div>`;
+        }
+
+        // Commits ahead of/behind the upstream branch, hidden when in sync or without one
+        function upstreamBadge(repo) {
+            const { ahead, behind } = repo.repo_info;
+            if (ahead == null || (ahead === 0 && behind === 0)) return '';
+            return `<span class="upstream-badge" title="${ahead} ahead of, ${behind} behind upstream">↑${ahead} ↓${behind}</span>`;
+        }
+
+        // Badge for an acknowledged failure, or a button to acknowledge one
+        function acknowledgement(repo) {
+            if (repo.acknowledged) return '<span class="ack-badge" title="Hidden from the failing count until the next successful build">acknowledged</span>';
+            if (!isFailing(repo)) return '';
+            return `<button class="btn btn-secondary" data-repo="${escapeHtml(repo.repository.name)}" onclick="acknowledgeRepository(this.dataset.repo)" title="Acknowledge this failure">Ack</button>`;
+        }
+
+        function isFailing(repo) {
+            const status = repo.current_status;
+            return status === 'Failed' || status === 'Unstable' || status === 'NoCommands' || status.startsWith('Error') || status.startsWith('AuthError');
+        }
+
+        async function acknowledgeRepository(name) {
+            try {
+                await fetchJson(`/api/repository/${encodeURIComponent(name)}/acknowledge`, { method: 'POST' });
+            } catch (error) {
+                alert(`Failed to acknowledge: ${error.message}`);
+            }
+            loadAllData();
+        }
+
+        function statusClass(repo) {
+            return repo.is_building ? 'building' : repo.current_status.toLowerCase().split(':')[0];
+        }
+
+        let overviewSort = localStorage.getItem('turbulent-overview-sort') || 'health';
+
+        function setOverviewSort(sort) {
+            overviewSort = sort;
+            localStorage.setItem('turbulent-overview-sort', sort);
+            renderRepositoryOverview();
+        }
+
+        // The least healthy repositories first, for triage, or alphabetical
+        function sortForOverview(repos) {
+            document.querySelectorAll('.overview-sort .filter-btn').forEach(btn => {
+                btn.classList.toggle('active', btn.dataset.sort === overviewSort);
+            });
+            const byName = (a, b) => a.repository.name.localeCompare(b.repository.name);
+            if (overviewSort === 'name') return [...repos].sort(byName);
+            return [...repos].sort((a, b) => a.health_score - b.health_score || byName(a, b));
+        }
+
         function renderSummaryStats() {
             const container = document.getElementById('summary-stats');
 
             const totalRepos = repositories.length;
             const passingRepos = repositories.filter(r => r.current_status === 'Passing').length;
-            const failingRepos = repositories.filter(r => r.current_status === 'Failed').length;
+            const failingRepos = repositories.filter(r => r.current_status === 'Failed' && !r.acknowledged).length;
+            const unstableRepos = repositories.filter(r => r.current_status === 'Unstable').length;
+            const buildingRepos = repositories.filter(r => r.is_building).length;
             const totalBuilds = recentBuilds.length;
             const successRate = totalBuilds > 0 ? Math.round((recentBuilds.filter(b => b.success).length / totalBuilds) * 100) : 0;
 
@@ -324,6 +1272,14 @@ const HTML_TEMPLATE: &str = r#"
                     <div class="stat-number" style="color: #dc2626;">${failingRepos}</div>
                     <div class="stat-label">Failing</div>
                 </div>
+                <div class="stat-card">
+                    <div class="stat-number" style="color: #ea580c;">${unstableRepos}</div>
+                    <div class="stat-label">Unstable</div>
+                </div>
+                <div class="stat-card">
+                    <div class="stat-number" style="color: #d97706;">${buildingRepos}</div>
+                    <div class="stat-label">Building</div>
+                </div>
                 <div class="stat-card">
                     <div class="stat-number" style="color: #3b82f6;">${totalBuilds}</div>
                     <div class="stat-label">Total Builds</div>
@@ -343,20 +1299,30 @@ const HTML_TEMPLATE: &str = r#"
                 return;
             }
 
-            container.innerHTML = repositories.map(repo => {
+            const visible = sortForOverview(visibleRepositories());
+            if (visible.length === 0) {
+                container.innerHTML = noMatchingRepositories();
+                return;
+            }
+
+            container.innerHTML = visible.map(repo => {
                 const recentBuilds = repo.builds.slice(0, 3);
                 return `
-                    <div class="repo-card">
+                    <div class="repo-card ${repo.acknowledged ? 'acknowledged' : ''}">
                         <div class="repo-header">
                             <div>
                                 <div class="repo-name">${repo.repository.name}</div>
                                 <div class="repo-path">${repo.repository.path}</div>
                             </div>
                             <div class="repo-status">
-                                <span class="project-type">${repo.repository.project_type}</span>
-                                <span class="status ${repo.current_status.toLowerCase()}">${repo.current_status}</span>
+                                ${upstreamBadge(repo)}
+                                ${repo.repo_info.dirty ? `<span class="dirty-badge" title="${repo.repo_info.changed_files} uncommitted change(s)">dirty</span>` : ''}
+                                ${acknowledgement(repo)}
+                                <span class="project-type ${repo.repository.project_type.toLowerCase()}">${repo.repository.project_type}</span>
+                                <span role="status" class="status ${statusClass(repo)}">${repo.current_status}</span>
                             </div>
                         </div>
+                        ${progressBar(repo)}
 
                         <div class="repo-info">
                             <div class="repo-detail">
@@ -365,22 +1331,30 @@ const HTML_TEMPLATE: &str = r#"
                             </div>
                             <div class="repo-detail">
                                 <strong>Last Commit</strong>
-                                <div>${repo.repo_info.last_commit.substring(0, 8)}</div>
+                                <div>${shortHash(repo.repo_info.last_commit)}</div>
+                            </div>
+                            <div class="repo-detail">
+                                <strong>Health</strong>
+                                <div title="Weighted recent success rate and current status">${repo.health_score}/100</div>
                             </div>
                         </div>
+                        ${repo.repo_info.skipped_commit ? `
+                        <div style="color: var(--text-muted); font-size: 12px; margin-bottom: 12px;">
+                            ⏭️ Skipped ${shortHash(repo.repo_info.skipped_commit.commit_hash)} (${escapeHtml(repo.repo_info.skipped_commit.reason)})
+                        </div>` : ''}
 
                         <div class="recent-builds">
                             <h4>Recent Builds</h4>
                             ${recentBuilds.length > 0 ? recentBuilds.map(build => `
                                 <div class="build-item-small">
-                                    <span class="build-icon">${build.success ? '✅' : '❌'}</span>
+                                    <span class="build-icon">${buildIcon(build)}</span>
                                     <div class="build-info-small">
-                                        <div class="build-id">Build #${build.id}</div>
-                                        <div class="build-time">${new Date(build.timestamp * 1000).toLocaleString()}</div>
+                                        <div class="build-id" title="Build ID ${build.id}">#${build.repo_build_number}${build.subproject ? ` · ${escapeHtml(build.subproject)}` : ''}${build.slow ? ' <span class="slow-build" title="Slower than its baseline">🐢</span>' : ''}</div>
+                                        <div class="build-time">${formatTime(build.timestamp)}</div>
                                     </div>
                                     <button class="btn btn-secondary" onclick="showBuildDetails(${build.id})">View</button>
                                 </div>
-                            `).join('') : '<div style="color: #64748b; font-size: 12px;">No builds yet</div>'}
+                            `).join('') : '<div style="color: var(--text-muted); font-size: 12px;">No builds yet</div>'}
                         </div>
                     </div>
                 `;
@@ -395,18 +1369,28 @@ const HTML_TEMPLATE: &str = r#"
                 return;
             }
 
-            container.innerHTML = repositories.map(repo => `
-                <div class="repo-card">
+            const visible = visibleRepositories();
+            if (visible.length === 0) {
+                container.innerHTML = noMatchingRepositories();
+                return;
+            }
+
+            container.innerHTML = visible.map(repo => `
+                <div class="repo-card ${repo.acknowledged ? 'acknowledged' : ''}">
                     <div class="repo-header">
                         <div>
                             <div class="repo-name">${repo.repository.name}</div>
                             <div class="repo-path">${repo.repository.path}</div>
                         </div>
                         <div class="repo-status">
-                            <span class="project-type">${repo.repository.project_type}</span>
-                            <span class="status ${repo.current_status.toLowerCase()}">${repo.current_status}</span>
+                            ${upstreamBadge(repo)}
+                            ${repo.repo_info.dirty ? `<span class="dirty-badge" title="${repo.repo_info.changed_files} uncommitted change(s)">dirty</span>` : ''}
+                            ${acknowledgement(repo)}
+                            <span class="project-type ${repo.repository.project_type.toLowerCase()}">${repo.repository.project_type}</span>
+                            <span role="status" class="status ${statusClass(repo)}">${repo.current_status}</span>
                         </div>
                     </div>
+                    ${progressBar(repo)}
 
                     <div class="repo-info">
                         <div class="repo-detail">
@@ -415,7 +1399,7 @@ const HTML_TEMPLATE: &str = r#"
                         </div>
                         <div class="repo-detail">
                             <strong>Last Commit</strong>
-                            <div>${repo.repo_info.last_commit}</div>
+                            <div>${shortHash(repo.repo_info.last_commit)}</div>
                         </div>
                         <div class="repo-detail">
                             <strong>Total Builds</strong>
@@ -427,16 +1411,52 @@ const HTML_TEMPLATE: &str = r#"
                         </div>
                     </div>
 
+                    ${repo.repository.subprojects.length > 0 ? `
+                        <div style="margin-top: 16px;">
+                            <strong style="color: var(--text-secondary); font-size: 12px; text-transform: uppercase;">Subprojects:</strong>
+                            ${repo.repository.subprojects.map(sub => {
+                                const latest = repo.builds.find(b => b.subproject === sub.name);
+                                return `
+                                    <div class="build-item-small">
+                                        <span class="build-icon">${latest ? buildIcon(latest) : '⏳'}</span>
+                                        <div class="build-info-small">
+                                            <div class="build-id">${escapeHtml(sub.name)} <span class="project-type ${sub.project_type.toLowerCase()}">${sub.project_type}</span></div>
+                                            <div class="build-time">${escapeHtml(sub.work_dir)}</div>
+                                        </div>
+                                        ${latest ? `<button class="btn btn-secondary" onclick="showBuildDetails(${latest.id})">View</button>` : ''}
+                                    </div>
+                                `;
+                            }).join('')}
+                        </div>
+                    ` : ''}
+
                     <div style="margin-top: 16px;">
-                        <strong style="color: #475569; font-size: 12px; text-transform: uppercase;">Commands:</strong>
-                        <div style="margin-top: 8px; font-family: 'SF Mono', Monaco, monospace; font-size: 12px; background: #f8fafc; padding: 12px; border-radius: 6px; border: 1px solid #e2e8f0;">
-                            ${repo.repository.commands.map(cmd => `<div>• ${cmd}</div>`).join('')}
+                        <strong style="color: var(--text-secondary); font-size: 12px; text-transform: uppercase;">Commands:</strong>
+                        <div style="margin-top: 8px; font-family: 'SF Mono', Monaco, monospace; font-size: 12px; background: var(--bg); padding: 12px; border-radius: 6px; border: 1px solid var(--border);">
+                            ${repo.repository.commands.map(cmd => cmd.name === cmd.cmd ? `<div>• ${escapeHtml(cmd.cmd)}</div>` : `<div>• <strong>${escapeHtml(cmd.name)}</strong>: ${escapeHtml(cmd.cmd)}</div>`).join('')}
                         </div>
                     </div>
                 </div>
             `).join('');
         }
 
+        function renderEvents() {
+            const container = document.getElementById('events-container');
+            if (events.length === 0) {
+                container.innerHTML = '<div class="empty-state">📜 No events yet</div>';
+                return;
+            }
+
+            container.innerHTML = events.map(event => `
+                <div class="event-item">
+                    <span class="build-time">${formatTime(event.timestamp)}</span>
+                    <span class="trigger-badge">${escapeHtml(event.type.replace(/_/g, ' '))}</span>
+                    ${event.repository ? `<strong>${escapeHtml(event.repository)}</strong>` : ''}
+                    <span>${escapeHtml(event.message)}</span>
+                </div>
+            `).join('');
+        }
+
         function renderBuilds() {
             const container = document.getElementById('builds-container');
 
@@ -454,18 +1474,22 @@ const HTML_TEMPLATE: &str = r#"
 
             container.innerHTML = filteredBuilds.map(build => `
                 <div class="build-item">
+                    <input type="checkbox" class="compare-checkbox" ${selectedBuilds.includes(build.id) ? 'checked' : ''} onchange="toggleCompareSelection(${build.id}, this.checked)" title="Select for comparison">
                     <div class="build-info">
                         <div class="build-header">
-                            <span style="font-size: 18px;">${build.success ? '✅' : '❌'}</span>
+                            <span style="font-size: 18px;">${buildIcon(build)}</span>
                             <strong style="font-size: 16px;">Build #${build.id}</strong>
-                            <span class="status ${build.success ? 'passing' : 'failed'}">${build.success ? 'Passed' : 'Failed'}</span>
-                            <span style="background: #f1f5f9; color: #475569; padding: 4px 8px; border-radius: 12px; font-size: 11px; font-weight: 600;">${build.repository_name}</span>
+                            <span role="status" class="status ${buildStatus(build)[0]}">${buildStatus(build)[1]}</span>
+                            <span class="trigger-badge">${triggerLabel(build.trigger)}</span>
+                            <span style="background: var(--border-subtle); color: var(--text-secondary); padding: 4px 8px; border-radius: 12px; font-size: 11px; font-weight: 600;">${build.repository_name} #${build.repo_build_number}${build.subproject ? ` / ${escapeHtml(build.subproject)}` : ''}</span>
                         </div>
                         <div class="build-meta">
-                            <span>📋 ${build.commit_hash.substring(0, 8)}</span>
-                            <span>🕐 ${new Date(build.timestamp * 1000).toLocaleString()}</span>
-                            <span>⏱️ ${build.duration_ms}ms</span>
+                            <span>📋 ${shortHash(build.commit_hash)}</span>
+                            <span>🕐 ${formatTime(build.timestamp)}</span>
+                            <span ${build.slow ? `class="slow-build" title="Baseline ${build.baseline_duration_ms}ms"` : ''}>${build.slow ? '🐢' : '⏱️'} ${build.duration_ms}ms</span>
                             <span>📁 ${build.repo_path}</span>
+                            ${build.test_summary ? `<span>🧪 ${formatTestSummary(build.test_summary)}</span>` : ''}
+                            ${build.note ? `<span title="Note">📝 ${escapeHtml(build.note)}</span>` : ''}
                         </div>
                     </div>
                     <div class="build-actions">
@@ -475,6 +1499,69 @@ const HTML_TEMPLATE: &str = r#"
             `).join('');
         }
 
+        function toggleCompareSelection(buildId, checked) {
+            selectedBuilds = selectedBuilds.filter(id => id !== buildId);
+            if (checked) {
+                selectedBuilds.push(buildId);
+            }
+            // Only the two most recent selections are compared
+            if (selectedBuilds.length > 2) {
+                selectedBuilds.shift();
+                renderBuilds();
+            }
+
+            const button = document.getElementById('compare-btn');
+            button.textContent = `Compare (${selectedBuilds.length}/2)`;
+            button.disabled = selectedBuilds.length !== 2;
+        }
+
+        async function compareSelectedBuilds() {
+            const [a, b] = selectedBuilds;
+            try {
+                const comparison = await fetchJson(`/api/builds/compare?a=${a}&b=${b}`);
+
+                const formatDelta = ms => ms === null ? 'n/a' : `${ms > 0 ? '+' : ''}${ms}ms`;
+                const icon = value => value === null ? '—' : (value ? '✅' : '❌');
+
+                document.getElementById('build-details').innerHTML = `
+                    <h3 style="color: var(--text); margin-bottom: 16px;">Build #${comparison.a.id} vs Build #${comparison.b.id}</h3>
+                    <div class="repo-info">
+                        <div class="repo-detail">
+                            <strong>Result</strong>
+                            <div>${icon(comparison.a.success)} → ${icon(comparison.b.success)}${comparison.success_changed ? ' (changed)' : ''}</div>
+                        </div>
+                        <div class="repo-detail">
+                            <strong>Duration</strong>
+                            <div>${comparison.a.duration_ms}ms → ${comparison.b.duration_ms}ms (${formatDelta(comparison.duration_delta_ms)})</div>
+                        </div>
+                        <div class="repo-detail">
+                            <strong>Commits</strong>
+                            <div>${shortHash(comparison.a.commit_hash)} → ${shortHash(comparison.b.commit_hash)}</div>
+                        </div>
+                        <div class="repo-detail">
+                            <strong>Repositories</strong>
+                            <div>${comparison.a.repository_name} → ${comparison.b.repository_name}</div>
+                        </div>
+                    </div>
+                    <h4 style="color: var(--text); margin-bottom: 12px;">Commands</h4>
+                    ${comparison.commands.length > 0 ? comparison.commands.map(cmd => `
+                        <div class="build-item-small">
+                            <span class="build-icon">${icon(cmd.a_success)} → ${icon(cmd.b_success)}</span>
+                            <div class="build-info-small">
+                                <div class="build-id" title="${escapeHtml(cmd.command)}">${escapeHtml(cmd.name || cmd.command)}</div>
+                                <div class="build-time">${cmd.success_changed ? 'Result changed · ' : ''}${formatDelta(cmd.duration_delta_ms)}</div>
+                            </div>
+                        </div>
+                    `).join('') : '<div style="color: var(--text-muted); font-size: 12px;">No per-command results recorded</div>'}
+                `;
+
+                openModal();
+            } catch (error) {
+                console.error('Failed to compare builds:', error);
+                alert(`Failed to compare builds: ${error.message}`);
+            }
+        }
+
         function filterBuilds(filter) {
             currentFilter = filter;
             document.querySelectorAll('.filter-btn').forEach(btn => btn.classList.remove('active'));
@@ -484,18 +1571,12 @@ const HTML_TEMPLATE: &str = r#"
 
         async function showBuildDetails(buildId) {
             try {
-                const response = await fetch(`/api/build/${buildId}`);
-                const build = await response.json();
-
-                if (build.error) {
-                    alert('Build not found');
-                    return;
-                }
+                const build = await fetchJson(`/api/build/${buildId}`);
 
                 const details = document.getElementById('build-details');
                 details.innerHTML = `
                     <div style="margin-bottom: 24px;">
-                        <h3 style="color: #1e293b; margin-bottom: 16px;">Build #${build.id} ${build.success ? '✅' : '❌'}</h3>
+                        <h3 style="color: var(--text); margin-bottom: 16px;">Build #${build.id} ${buildIcon(build)} <span style="color: var(--text-muted); font-size: 14px; font-weight: normal;">${build.repository_name} #${build.repo_build_number}</span></h3>
                         <div class="repo-info">
                             <div class="repo-detail">
                                 <strong>Repository</strong>
@@ -511,32 +1592,329 @@ const HTML_TEMPLATE: &str = r#"
                             </div>
                             <div class="repo-detail">
                                 <strong>Started</strong>
-                                <div>${new Date(build.timestamp * 1000).toLocaleString()}</div>
+                                <div>${formatTime(build.timestamp)}</div>
                             </div>
                             <div class="repo-detail">
                                 <strong>Duration</strong>
-                                <div>${build.duration_ms}ms</div>
+                                <div>${build.duration_ms}ms ${formatBaseline(build)}</div>
                             </div>
+                            <div class="repo-detail">
+                                <strong>Peak Memory</strong>
+                                <div>${formatMemory(build.peak_memory_kb)}</div>
+                            </div>
+                            <div class="repo-detail">
+                                <strong>CPU Time</strong>
+                                <div>${build.cpu_time_ms == null ? 'n/a' : build.cpu_time_ms + 'ms'}</div>
+                            </div>
+                            <div class="repo-detail">
+                                <strong>Trigger</strong>
+                                <div><span class="trigger-badge">${triggerLabel(build.trigger)}</span></div>
+                            </div>
+                            ${build.test_summary ? `
+                                <div class="repo-detail">
+                                    <strong>Tests</strong>
+                                    <div>${formatTestSummary(build.test_summary)}</div>
+                                </div>
+                            ` : ''}
+                            ${build.rerun_of ? `
+                            <div class="repo-detail">
+                                <strong>Re-run Of</strong>
+                                <div>Build #${build.rerun_of}</div>
+                            </div>` : ''}
+                            ${build.subproject ? `
+                            <div class="repo-detail">
+                                <strong>Subproject</strong>
+                                <div>${escapeHtml(build.subproject)}</div>
+                            </div>` : ''}
                             <div class="repo-detail">
                                 <strong>Project Type</strong>
-                                <div><span class="project-type">${build.project_type}</span></div>
+                                <div><span class="project-type ${build.project_type.toLowerCase()}">${build.project_type}</span></div>
                             </div>
                         </div>
                     </div>
-                    <h4 style="color: #1e293b; margin-bottom: 12px;">Build Output:</h4>
-                    <div class="output">${build.output || 'No output available'}</div>
+                    <h4 style="color: var(--text); margin-bottom: 12px;">Note:</h4>
+                    <div class="output-toolbar">
+                        <input type="text" id="build-note" class="output-search" placeholder="e.g. known flake, ignore" value="${escapeHtml(build.note || '')}">
+                        <button class="btn btn-secondary" onclick="saveBuildNote(${build.id})">Save</button>
+                        <span id="build-note-status" class="output-matches"></span>
+                    </div>
+                    ${build.environment && Object.keys(build.environment).length > 0 ? `
+                        <h4 style="color: var(--text); margin-bottom: 12px;">Environment:</h4>
+                        <div class="environment-list">
+                            ${Object.keys(build.environment).sort().map(cmd => `
+                                <div class="environment-item">
+                                    <code>${escapeHtml(cmd)}</code>
+                                    <span>${escapeHtml(build.environment[cmd])}</span>
+                                </div>
+                            `).join('')}
+                        </div>
+                    ` : ''}
+                    ${build.steps && build.steps.length > 0 ? `
+                        <h4 style="color: var(--text); margin-bottom: 12px;">Steps:</h4>
+                        <div style="margin-bottom: 24px;">
+                            ${build.steps.map(renderStep).join('')}
+                        </div>
+                    ` : ''}
+                    <h4 style="color: var(--text); margin-bottom: 12px;">Build Output:</h4>
+                    <div class="output-toolbar">
+                        <input type="text" id="output-search" class="output-search" placeholder="Search output..." oninput="renderOutput()">
+                        <span id="output-matches" class="output-matches"></span>
+                        <button class="btn btn-secondary" onclick="jumpToFirstError()">Jump to first error</button>
+                        <a class="btn btn-secondary" href="/api/build/${build.id}/download">Download log</a>
+                    </div>
+                    <div class="output" id="build-output"></div>
                 `;
 
-                document.getElementById('build-modal').style.display = 'block';
+                currentOutput = build.output || 'No output available';
+                renderOutput();
+
+                openModal();
             } catch (error) {
                 console.error('Failed to load build details:', error);
+                alert(`Failed to load build details: ${error.message}`);
+            }
+        }
+
+        // One command of a build; its own output, when recorded, folds out
+        // and starts open if the command failed
+        function renderStep(step) {
+            const summary = `
+                <span class="build-icon">${step.skipped ? '⏭️' : step.success ? '✅' : '❌'}</span>
+                <div class="build-info-small">
+                    <div class="build-id" title="${escapeHtml(step.command)}">${escapeHtml(step.name || step.command)}</div>
+                    <div class="build-time">${step.skipped ? 'skipped (not installed)' : step.duration_ms + 'ms'}</div>
+                </div>
+            `;
+            if (!step.output) {
+                return `<div class="build-item-small">${summary}</div>`;
+            }
+            // With output_mode separate, each stream gets its own block
+            const separate = step.stdout != null || step.stderr != null;
+            const body = separate
+                ? [['stdout', step.stdout], ['stderr', step.stderr]]
+                    .filter(([, text]) => text)
+                    .map(([label, text]) => `<div class="step-stream">${label}</div><div class="output step-output">${escapeHtml(text)}</div>`)
+                    .join('') || '<div class="build-time">No output</div>'
+                : `<div class="output step-output">${escapeHtml(step.output)}</div>`;
+            return `
+                <details class="step-section" ${step.success ? '' : 'open'}>
+                    <summary class="build-item-small">${summary}</summary>
+                    ${body}
+                </details>
+            `;
+        }
+
+        async function saveBuildNote(buildId) {
+            const status = document.getElementById('build-note-status');
+            try {
+                await fetchJson(`/api/build/${buildId}/note`, {
+                    method: 'POST',
+                    headers: { 'Content-Type': 'text/plain' },
+                    body: document.getElementById('build-note').value
+                });
+                status.textContent = 'Saved';
+                loadAllData();
+            } catch (error) {
+                status.textContent = `Failed to save: ${error.message}`;
+            }
+        }
+
+        function applyTheme(theme) {
+            document.documentElement.setAttribute('data-theme', theme);
+            document.getElementById('theme-toggle').textContent = theme === 'dark' ? '☀️' : '🌙';
+            localStorage.setItem('turbulent-theme', theme);
+        }
+
+        function toggleTheme() {
+            const current = document.documentElement.getAttribute('data-theme');
+            applyTheme(current === 'dark' ? 'light' : 'dark');
+        }
+
+        function triggerLabel(trigger) {
+            const labels = {
+                Commit: '📝 Commit',
+                Manual: '🚀 Manual',
+                Webhook: '🔗 Webhook',
+                Scheduled: '⏰ Scheduled',
+                Dependency: '🧩 Dependency',
+            };
+            return labels[trigger] || trigger || 'Unknown';
+        }
+
+        function formatBaseline(build) {
+            if (build.baseline_duration_ms == null || build.baseline_duration_ms === 0) return '';
+            const percent = Math.round((build.duration_ms / build.baseline_duration_ms - 1) * 100);
+            return `<span class="${build.slow ? 'slow-build' : 'build-time'}">(${percent > 0 ? '+' : ''}${percent}% vs ${build.baseline_duration_ms}ms baseline)</span>`;
+        }
+
+        // Relative time with the absolute one on hover
+        function formatTime(timestamp) {
+            const date = new Date(timestamp * 1000);
+            const seconds = Math.floor((Date.now() - date.getTime()) / 1000);
+            let relative;
+            if (seconds < 45) {
+                relative = 'just now';
+            } else if (seconds < 3600) {
+                relative = `${Math.max(1, Math.round(seconds / 60))}m ago`;
+            } else if (seconds < 86400) {
+                relative = `${Math.floor(seconds / 3600)}h ago`;
+            } else {
+                const startOfToday = new Date();
+                startOfToday.setHours(0, 0, 0, 0);
+                const days = Math.ceil((startOfToday - date) / 86400000);
+                if (days <= 1) relative = 'yesterday';
+                else if (days < 7) relative = `${days}d ago`;
+                else relative = date.toLocaleDateString();
+            }
+            return `<span title="${date.toLocaleString()}">${relative}</span>`;
+        }
+
+        function formatTestSummary(summary) {
+            const parts = [`${summary.passed} passed`];
+            if (summary.failed > 0) parts.push(`${summary.failed} failed`);
+            if (summary.skipped > 0) parts.push(`${summary.skipped} skipped`);
+            return parts.join(', ');
+        }
+
+        function formatMemory(kb) {
+            if (kb == null) return 'n/a';
+            if (kb >= 1024 * 1024) return (kb / 1024 / 1024).toFixed(1) + ' GB';
+            if (kb >= 1024) return (kb / 1024).toFixed(1) + ' MB';
+            return kb + ' KB';
+        }
+
+        function escapeHtml(text) {
+            return text
+                .replace(/&/g, '&amp;')
+                .replace(/</g, '&lt;')
+                .replace(/>/g, '&gt;')
+                .replace(/"/g, '&quot;');
+        }
+
+        function renderOutput() {
+            const query = document.getElementById('output-search').value;
+            const pattern = query ? new RegExp(query.replace(/[.*+?^${}()|[\]\\]/g, '\\$&'), 'gi') : null;
+            let matches = 0;
+
+            document.getElementById('build-output').innerHTML = currentOutput.split('\n').map((line, index) => {
+                let html = escapeHtml(line);
+                if (pattern) {
+                    // Highlight on the raw line, escaping the text around each match
+                    const parts = line.split(pattern);
+                    const found = line.match(pattern) || [];
+                    matches += found.length;
+                    html = parts.map((part, i) => escapeHtml(part) + (i < found.length ? `<mark>${escapeHtml(found[i])}</mark>` : '')).join('');
+                }
+                return `<div class="output-line" data-line="${index}">${html || ' '}</div>`;
+            }).join('');
+
+            document.getElementById('output-matches').textContent = pattern ? `${matches} match${matches === 1 ? '' : 'es'}` : '';
+            const firstMatch = document.querySelector('#build-output mark');
+            if (firstMatch) {
+                firstMatch.scrollIntoView({ block: 'center' });
+            }
+        }
+
+        function jumpToFirstError() {
+            const lines = currentOutput.split('\n');
+            const index = lines.findIndex(line => /error|failed/i.test(line));
+            if (index === -1) {
+                return;
+            }
+
+            document.querySelectorAll('#build-output .error-line').forEach(el => el.classList.remove('error-line'));
+            const target = document.querySelector(`#build-output [data-line="${index}"]`);
+            target.classList.add('error-line');
+            target.scrollIntoView({ block: 'center' });
+        }
+
+        let focusBeforeModal = null;
+
+        function openModal() {
+            const modal = document.getElementById('build-modal');
+            if (modal.style.display !== 'block') {
+                focusBeforeModal = document.activeElement;
             }
+            modal.style.display = 'block';
+            const focusable = modalFocusable();
+            if (focusable.length > 0) focusable[0].focus();
         }
 
         function closeModal() {
             document.getElementById('build-modal').style.display = 'none';
+            if (focusBeforeModal) {
+                focusBeforeModal.focus();
+                focusBeforeModal = null;
+            }
+        }
+
+        function isModalOpen() {
+            return document.getElementById('build-modal').style.display === 'block';
         }
 
+        function modalFocusable() {
+            const selector = 'button, a[href], input, textarea, select, [tabindex]:not([tabindex="-1"])';
+            return [...document.querySelectorAll('#build-modal .modal-content ' + selector)]
+                .filter(el => !el.disabled && el.offsetParent !== null);
+        }
+
+        const TAB_KEYS = ['overview', 'projects', 'repositories', 'builds', 'events'];
+
+        function focusTab(tabName) {
+            switchTab(tabName);
+            document.querySelector(`.nav-tab[data-tab="${tabName}"]`).focus();
+        }
+
+        // Number keys switch tabs and `r` refreshes; while the build modal is
+        // open, Escape closes it and Tab stays inside it
+        document.addEventListener('keydown', event => {
+            if (isModalOpen()) {
+                if (event.key === 'Escape') {
+                    event.preventDefault();
+                    closeModal();
+                } else if (event.key === 'Tab') {
+                    const focusable = modalFocusable();
+                    if (focusable.length === 0) return;
+                    const first = focusable[0];
+                    const last = focusable[focusable.length - 1];
+                    const inside = focusable.includes(document.activeElement);
+                    if (event.shiftKey && (document.activeElement === first || !inside)) {
+                        event.preventDefault();
+                        last.focus();
+                    } else if (!event.shiftKey && (document.activeElement === last || !inside)) {
+                        event.preventDefault();
+                        first.focus();
+                    }
+                }
+                return;
+            }
+
+            const target = event.target;
+            if (event.ctrlKey || event.metaKey || event.altKey
+                || ['INPUT', 'TEXTAREA', 'SELECT'].includes(target.tagName) || target.isContentEditable) {
+                return;
+            }
+            if (target.classList && target.classList.contains('nav-tab')) {
+                const current = TAB_KEYS.indexOf(target.dataset.tab);
+                if (event.key === 'Enter' || event.key === ' ') {
+                    event.preventDefault();
+                    switchTab(target.dataset.tab);
+                    return;
+                } else if (event.key === 'ArrowRight' || event.key === 'ArrowLeft') {
+                    event.preventDefault();
+                    const step = event.key === 'ArrowRight' ? 1 : TAB_KEYS.length - 1;
+                    focusTab(TAB_KEYS[(current + step) % TAB_KEYS.length]);
+                    return;
+                }
+            }
+            const index = Number(event.key) - 1;
+            if (Number.isInteger(index) && index >= 0 && index < TAB_KEYS.length) {
+                focusTab(TAB_KEYS[index]);
+            } else if (event.key === 'r') {
+                loadAllData();
+            }
+        });
+
         // Close modal when clicking outside
         window.onclick = function(event) {
             const modal = document.getElementById('build-modal');
@@ -545,11 +1923,66 @@ const HTML_TEMPLATE: &str = r#"
             }
         }
 
-        // Auto-refresh every 15 seconds
-        setInterval(loadAllData, 15000);
+        let refreshIntervalSecs = 15;
+        let refreshTimer = null;
+
+        function startAutoRefresh() {
+            stopAutoRefresh();
+            refreshTimer = setInterval(loadAllData, refreshIntervalSecs * 1000);
+        }
+
+        function stopAutoRefresh() {
+            if (refreshTimer !== null) {
+                clearInterval(refreshTimer);
+                refreshTimer = null;
+            }
+        }
+
+        function toggleAutoRefresh() {
+            const button = document.getElementById('pause-btn');
+            if (refreshTimer !== null) {
+                stopAutoRefresh();
+                button.textContent = '▶️';
+                button.title = 'Resume auto-refresh';
+                button.classList.add('paused');
+            } else {
+                startAutoRefresh();
+                loadAllData();
+                button.textContent = '⏸️';
+                button.title = 'Pause auto-refresh';
+                button.classList.remove('paused');
+            }
+        }
+
+        let commitHashLength = 8;
+
+        // Abbreviated hash with the full one on hover
+        function shortHash(hash) {
+            if (!hash || hash === 'unknown') return hash;
+            return `<span title="${hash}">${hash.substring(0, commitHashLength)}</span>`;
+        }
+
+        async function loadUiConfig() {
+            try {
+                const config = await fetchJson('/api/config');
+                if (config.refresh_interval_secs > 0) {
+                    refreshIntervalSecs = config.refresh_interval_secs;
+                }
+                if (config.commit_hash_length > 0) {
+                    commitHashLength = config.commit_hash_length;
+                }
+            } catch (error) {
+                console.error('Failed to load dashboard config:', error);
+            }
+        }
+
+        applyTheme(localStorage.getItem('turbulent-theme') || 'light');
 
-        // Initial load
-        loadAllData();
+        // Initial load, then auto-refresh at the configured interval
+        loadUiConfig().then(() => {
+            startAutoRefresh();
+            loadAllData();
+        });
     </script>
 </body>
 </html>
@@ -0,0 +1,128 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use warp::http::HeaderMap;
+
+// Push notifications from code hosts, checked against the repository's
+// `webhook_secret` before they are allowed to wake its runner
+
+/// Sender of a webhook, told apart by the headers it sets
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Provider {
+    /// `X-Hub-Signature-256: sha256=<hex HMAC-SHA256 of the body>`
+    GitHub,
+    /// `X-Gitlab-Token: <secret>`
+    GitLab,
+}
+
+impl Provider {
+    pub fn detect(headers: &HeaderMap) -> Option<Self> {
+        if headers.contains_key("x-hub-signature-256") || headers.contains_key("x-github-event") {
+            Some(Provider::GitHub)
+        } else if headers.contains_key("x-gitlab-token") || headers.contains_key("x-gitlab-event") {
+            Some(Provider::GitLab)
+        } else {
+            None
+        }
+    }
+}
+
+/// Whether the request was sent by someone knowing `secret`. Comparisons
+/// take the same time however much of the signature matches.
+pub fn verify_signature(provider: Provider, secret: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+    match provider {
+        Provider::GitHub => {
+            let Some(signature) = header(headers, "x-hub-signature-256")
+                .and_then(|value| value.strip_prefix("sha256="))
+                .and_then(decode_hex)
+            else {
+                return false;
+            };
+            let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+                return false;
+            };
+            mac.update(body);
+            mac.verify_slice(&signature).is_ok()
+        }
+        Provider::GitLab => header(headers, "x-gitlab-token")
+            .is_some_and(|token| constant_time_eq(token.as_bytes(), secret.as_bytes())),
+    }
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| hex.get(i..i + 2).and_then(|byte| u8::from_str_radix(byte, 16).ok()))
+        .collect()
+}
+
+// Only the length can be told from the timing, and tokens of the wrong
+// length are rejected anyway
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use warp::http::HeaderValue;
+
+    // The example from GitHub's "Validating webhook deliveries" documentation
+    const SECRET: &str = "It's a Secret to Everybody";
+    const BODY: &[u8] = b"Hello, World!";
+    const SIGNATURE: &str = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn detects_provider_from_headers() {
+        assert_eq!(Provider::detect(&headers(&[("x-hub-signature-256", SIGNATURE)])), Some(Provider::GitHub));
+        assert_eq!(Provider::detect(&headers(&[("x-github-event", "push")])), Some(Provider::GitHub));
+        assert_eq!(Provider::detect(&headers(&[("x-gitlab-token", SECRET)])), Some(Provider::GitLab));
+        assert_eq!(Provider::detect(&headers(&[("x-gitlab-event", "Push Hook")])), Some(Provider::GitLab));
+        assert_eq!(Provider::detect(&headers(&[("content-type", "application/json")])), None);
+    }
+
+    #[test]
+    fn github_accepts_valid_signature() {
+        let headers = headers(&[("x-hub-signature-256", SIGNATURE)]);
+        assert!(verify_signature(Provider::GitHub, SECRET, &headers, BODY));
+    }
+
+    #[test]
+    fn github_rejects_tampered_body_or_wrong_secret() {
+        let headers = headers(&[("x-hub-signature-256", SIGNATURE)]);
+        assert!(!verify_signature(Provider::GitHub, SECRET, &headers, b"Hello, World?"));
+        assert!(!verify_signature(Provider::GitHub, "another secret", &headers, BODY));
+    }
+
+    #[test]
+    fn github_rejects_missing_or_malformed_signature() {
+        let hex = SIGNATURE.trim_start_matches("sha256=");
+        for value in [hex, "sha1=757107ea", "sha256=", "sha256=zz", "sha256=7571070"] {
+            let headers = headers(&[("x-hub-signature-256", value)]);
+            assert!(!verify_signature(Provider::GitHub, SECRET, &headers, BODY), "accepted {:?}", value);
+        }
+        assert!(!verify_signature(Provider::GitHub, SECRET, &HeaderMap::new(), BODY));
+    }
+
+    #[test]
+    fn gitlab_compares_token() {
+        assert!(verify_signature(Provider::GitLab, SECRET, &headers(&[("x-gitlab-token", SECRET)]), BODY));
+        assert!(!verify_signature(Provider::GitLab, SECRET, &headers(&[("x-gitlab-token", "It's a Secret")]), BODY));
+        assert!(!verify_signature(Provider::GitLab, SECRET, &headers(&[("x-gitlab-token", "it's a secret to everybody")]), BODY));
+        assert!(!verify_signature(Provider::GitLab, SECRET, &HeaderMap::new(), BODY));
+    }
+}